@@ -0,0 +1,187 @@
+use anyhow::Result;
+use archipelago_rs as ap;
+
+use crate::slot_data::SlotData;
+
+/// The parts of the Archipelago client that [Core](crate::core::Core) uses to
+/// exchange game state with the server.
+///
+/// This exists so that the logic that decides what to send and receive can be
+/// tested against a mock client without a running server.
+pub trait ArchipelagoClient {
+    /// The name of the seed for the room this client is connected to.
+    fn seed_name(&self) -> &str;
+
+    /// The name of the player this client is connected as.
+    fn player_name(&self) -> &str;
+
+    /// The number of items the server has sent to this player.
+    fn received_item_count(&self) -> usize;
+
+    /// Returns whether the server considers [location] checked.
+    fn is_checked(&self, location: i64) -> bool;
+
+    /// Returns whether the server considers [location] a location in this
+    /// world that hasn't been checked yet.
+    fn is_missing(&self, location: i64) -> bool;
+
+    /// Tells the server that the player has checked [locations].
+    fn send_location_checks(&mut self, locations: Vec<i64>) -> Result<()>;
+
+    /// Sends a death link to the other players, blaming [cause].
+    fn send_death_link(&mut self, cause: String) -> Result<()>;
+
+    /// Tells the server that the player has achieved their goal.
+    fn send_goal(&mut self) -> Result<()>;
+}
+
+impl ArchipelagoClient for ap::Client<SlotData> {
+    fn seed_name(&self) -> &str {
+        ap::Client::seed_name(self)
+    }
+
+    fn player_name(&self) -> &str {
+        self.this_player().name()
+    }
+
+    fn received_item_count(&self) -> usize {
+        self.received_items().len()
+    }
+
+    fn is_checked(&self, location: i64) -> bool {
+        self.checked_locations().contains(&location)
+    }
+
+    fn is_missing(&self, location: i64) -> bool {
+        self.missing_locations().contains(&location)
+    }
+
+    fn send_location_checks(&mut self, locations: Vec<i64>) -> Result<()> {
+        Ok(self.mark_checked(locations)?)
+    }
+
+    fn send_death_link(&mut self, cause: String) -> Result<()> {
+        Ok(self.death_link(ap::DeathLinkOptions {
+            cause: Some(cause),
+            ..Default::default()
+        })?)
+    }
+
+    fn send_goal(&mut self) -> Result<()> {
+        Ok(self.set_status(ap::ClientStatus::Goal)?)
+    }
+}
+
+/// A fake client for tests.
+#[cfg(test)]
+pub mod mock {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// A message that [MockClient] was asked to send to the server.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Sent {
+        LocationChecks(Vec<i64>),
+        DeathLink(String),
+        Goal,
+    }
+
+    /// An in-memory [ArchipelagoClient] that records everything sent through
+    /// it rather than talking to a server.
+    #[derive(Debug, Default)]
+    pub struct MockClient {
+        /// The seed name of the simulated room.
+        pub seed: String,
+
+        /// The name of the simulated player.
+        pub player: String,
+
+        /// The number of items the simulated server has sent.
+        pub received_items: usize,
+
+        /// The locations the simulated server considers checked.
+        pub checked: HashSet<i64>,
+
+        /// The locations the simulated server considers missing.
+        pub missing: HashSet<i64>,
+
+        /// Everything sent through this client, in order.
+        pub sent: Vec<Sent>,
+    }
+
+    impl MockClient {
+        /// Creates a client connected to a room with [seed], where [missing]
+        /// are the locations that haven't been checked yet.
+        pub fn new(seed: &str, missing: impl IntoIterator<Item = i64>) -> Self {
+            Self {
+                seed: seed.to_string(),
+                player: "Ashen One".to_string(),
+                missing: missing.into_iter().collect(),
+                ..Default::default()
+            }
+        }
+
+        /// Simulates the server acknowledging every location check sent so
+        /// far, moving them from [missing] to [checked].
+        pub fn acknowledge(&mut self) {
+            for sent in &self.sent {
+                if let Sent::LocationChecks(locations) = sent {
+                    for location in locations {
+                        if self.missing.remove(location) {
+                            self.checked.insert(*location);
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Returns every batch of location checks sent so far, in order.
+        pub fn sent_locations(&self) -> Vec<Vec<i64>> {
+            self.sent
+                .iter()
+                .filter_map(|sent| match sent {
+                    Sent::LocationChecks(locations) => Some(locations.clone()),
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+
+    impl ArchipelagoClient for MockClient {
+        fn seed_name(&self) -> &str {
+            &self.seed
+        }
+
+        fn player_name(&self) -> &str {
+            &self.player
+        }
+
+        fn received_item_count(&self) -> usize {
+            self.received_items
+        }
+
+        fn is_checked(&self, location: i64) -> bool {
+            self.checked.contains(&location)
+        }
+
+        fn is_missing(&self, location: i64) -> bool {
+            self.missing.contains(&location)
+        }
+
+        fn send_location_checks(&mut self, locations: Vec<i64>) -> Result<()> {
+            self.sent.push(Sent::LocationChecks(locations));
+            Ok(())
+        }
+
+        fn send_death_link(&mut self, cause: String) -> Result<()> {
+            self.sent.push(Sent::DeathLink(cause));
+            Ok(())
+        }
+
+        fn send_goal(&mut self) -> Result<()> {
+            self.sent.push(Sent::Goal);
+            Ok(())
+        }
+    }
+}
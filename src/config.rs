@@ -1,37 +1,120 @@
-use std::{fs, io, path::PathBuf};
+use std::{fs, io, path::PathBuf, time::Duration};
 
-use anyhow::{Error, Result};
+use anyhow::{Error, Result, bail};
 use serde::{Deserialize, Serialize};
 
 use crate::utils;
 
+/// The default maximum length of a chat message, in characters.
+const DEFAULT_MAX_SAY_LENGTH: usize = 1000;
+
+/// The default minimum number of seconds between granting two trap items.
+const DEFAULT_TRAP_INTERVAL_SECS: u64 = 30;
+
+/// The default minimum number of milliseconds between granting two items.
+const DEFAULT_ITEM_GRANT_INTERVAL_MS: u64 = 1000;
+
+/// The default number of seconds after a death link during which no further
+/// death links are sent or received.
+const DEFAULT_DEATH_LINK_GRACE_PERIOD_SECS: u64 = 30;
+
+/// The default maximum number of log messages to keep in the overlay.
+///
+/// This is relatively low because imgui is not very efficient about not
+/// rendering the offscreen messages every frame, which can cause real slowdown
+/// over long runs with chatty connections.
+const DEFAULT_LOG_BUFFER_LIMIT: usize = 200;
+
+/// The default number of seconds to wait for a connection to be established.
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 15;
+
+/// Checks that [url] looks like an Archipelago room URL and returns it in
+/// normalized form, with surrounding whitespace and trailing slashes removed.
+///
+/// The URL must have a host and a numeric port. It may optionally start with
+/// `ws://` or `wss://`, but no other scheme. The error message is meant to be
+/// shown directly to the player.
+pub fn validate_url(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    let rest = match url.split_once("://") {
+        Some((scheme, rest)) if scheme == "ws" || scheme == "wss" => rest,
+        Some((scheme, _)) => {
+            bail!("Unsupported scheme \"{scheme}://\" — use ws://, wss://, or leave it off")
+        }
+        None => url,
+    };
+
+    if rest.is_empty() {
+        bail!("Missing host — try archipelago.gg:12345");
+    }
+
+    let Some((host, port)) = rest.rsplit_once(':') else {
+        bail!("Missing port — try {rest}:12345");
+    };
+    if host.is_empty() {
+        bail!("Missing host — try archipelago.gg:{port}");
+    } else if port.is_empty() {
+        bail!("Missing port — try {host}:12345");
+    } else if port.parse::<u16>().is_err() {
+        bail!("Invalid port \"{port}\" — the port should be a number like 12345");
+    }
+
+    Ok(url.to_string())
+}
+
 /// The configuration file for the DS3 Archipelago connection.
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct Config {
     url: String,
     slot: String,
     seed: String,
     client_version: Option<String>,
+    data_package_checksum: Option<String>,
     password: Option<String>,
+    #[serde(default)]
+    log_per_seed: bool,
+    max_say_length: Option<usize>,
+    goal_message: Option<String>,
+    death_link_message: Option<String>,
+    #[serde(default)]
+    persist_logs: bool,
+    log_buffer_limit: Option<usize>,
+    #[serde(default)]
+    log_checked_locations: bool,
+    trap_interval_secs: Option<u64>,
+    scheme_fallback: Option<bool>,
+    #[serde(default)]
+    connect_on_load: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    connect_timeout_secs: Option<u64>,
+    item_grant_interval_ms: Option<u64>,
+    death_link_grace_period_secs: Option<u64>,
+    send_death_links: Option<bool>,
+    receive_death_links: Option<bool>,
+    #[serde(default)]
+    release_on_goal: bool,
+    #[serde(default)]
+    collect_on_goal: bool,
+    #[serde(default)]
+    item_sound_cues: bool,
 }
 
 impl Config {
-    /// Loads the config from disk.
-    pub fn load() -> Result<Self> {
+    /// Loads the config from disk, or returns None if it doesn't exist.
+    ///
+    /// A config that exists but can't be parsed is still an error, since it's
+    /// more likely to need fixing than replacing.
+    pub fn load() -> Result<Option<Self>> {
         let path = Self::path()?;
         match fs::read_to_string(&path) {
-            Ok(text) => json::from_str(&text).map_err(|err| {
+            Ok(text) => json::from_str(&text).map(Some).map_err(|err| {
                 Error::from(err).context(format!(
                     "Failed to parse config file {}",
                     path.to_string_lossy()
                 ))
             }),
-            Err(err) if err.kind() == io::ErrorKind::NotFound => {
-                Err(Error::from(err).context(format!(
-                    "{} doesn't exist. Have you run randomizer\\DS3Randomizer.exe?",
-                    path.to_string_lossy(),
-                )))
-            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
             Err(err) => Err(Error::from(err).context(format!(
                 "Failed to load config file {}",
                 path.to_string_lossy()
@@ -60,18 +143,53 @@ impl Config {
         self.url = url.as_ref().to_string()
     }
 
+    /// Returns whether to retry a failed connection using insecure WebSockets
+    /// if it used secure WebSockets or vice versa. When this is enabled, URLs
+    /// without an explicit scheme try secure WebSockets first. Defaults to
+    /// true; set this to false to force the scheme in the URL, or to connect to
+    /// URLs without a scheme the way earlier versions did.
+    pub fn scheme_fallback(&self) -> bool {
+        self.scheme_fallback.unwrap_or(true)
+    }
+
+    /// Returns whether to wait until the player loads a save before connecting
+    /// to the server, rather than connecting as soon as the game starts. The
+    /// player can still connect early from the overlay. Defaults to false.
+    pub fn connect_on_load(&self) -> bool {
+        self.connect_on_load
+    }
+
+    /// Returns additional tags to send to the server when connecting, beyond
+    /// those the client manages itself. This is mostly useful for testing.
+    ///
+    /// Tags are only sent when a connection is established, so an existing
+    /// connection keeps the tags it started with.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
     /// Returns the slot that the config was created with, or None if it
     /// doesn't contain a slot.
     pub fn slot(&self) -> &str {
         self.slot.as_str()
     }
 
+    /// Sets the slot name in the config file.
+    pub fn set_slot(&mut self, slot: impl AsRef<str>) {
+        self.slot = slot.as_ref().to_string()
+    }
+
     /// Returns the seed that the config was created with, or None if it
     /// doesn't contain a seed.
     pub fn seed(&self) -> &str {
         self.seed.as_str()
     }
 
+    /// Sets the seed in the config file.
+    pub fn set_seed(&mut self, seed: impl AsRef<str>) {
+        self.seed = seed.as_ref().to_string()
+    }
+
     /// Returns the version of DS3Randomizer.exe that the config was created
     /// with, or None if it doesn't contain a version (such as for a local
     /// randomizer build).
@@ -79,9 +197,223 @@ impl Config {
         self.client_version.as_deref()
     }
 
+    /// Returns the checksum of the Dark Souls III data package that the static
+    /// randomizer saw when it generated the config, or None if it didn't record
+    /// one (such as for older randomizer versions).
+    pub fn data_package_checksum(&self) -> Option<&str> {
+        self.data_package_checksum.as_deref()
+    }
+
     /// Returns the password that the config was created with, or None if it
     /// doesn't contain a password.
+    ///
+    /// The Archipelago protocol has no session tokens, so this is re-sent on
+    /// every connection, including reconnects. It's never written anywhere
+    /// other than `apconfig.json` (where DS3Randomizer.exe put it) and is only
+    /// ever sent to the configured server. Since that file is plain text, room
+    /// passwords shouldn't be reused for anything sensitive.
     pub fn password(&self) -> Option<&str> {
         self.password.as_deref()
     }
+
+    /// Sets the password in the config file. An empty password is stored as
+    /// no password at all.
+    pub fn set_password(&mut self, password: Option<impl AsRef<str>>) {
+        self.password = password
+            .map(|password| password.as_ref().to_string())
+            .filter(|password| !password.is_empty());
+    }
+
+    /// Returns whether logs should be written to a separate directory for each
+    /// seed rather than a single date-based file.
+    pub fn log_per_seed(&self) -> bool {
+        self.log_per_seed
+    }
+
+    /// Returns the maximum number of characters the player may send in a
+    /// single chat message.
+    pub fn max_say_length(&self) -> usize {
+        self.max_say_length.unwrap_or(DEFAULT_MAX_SAY_LENGTH)
+    }
+
+    /// Returns the chat message to send when the player achieves their goal,
+    /// or None if no message should be sent. `{player}` and `{seed}` in the
+    /// message are replaced with the slot name and seed, respectively.
+    pub fn goal_message(&self) -> Option<&str> {
+        self.goal_message
+            .as_deref()
+            .filter(|message| !message.is_empty())
+    }
+
+    /// Returns the chat message to send when the player is killed by a death
+    /// link, or None if no message should be sent. `{source}` and `{cause}` in
+    /// the message are replaced with the name of the player who died and the
+    /// reason they gave (if any), respectively.
+    pub fn death_link_message(&self) -> Option<&str> {
+        self.death_link_message
+            .as_deref()
+            .filter(|message| !message.is_empty())
+    }
+
+    /// Returns whether the overlay's log messages should be saved to disk and
+    /// reloaded the next time the game starts.
+    pub fn persist_logs(&self) -> bool {
+        self.persist_logs
+    }
+
+    /// Returns the maximum number of log messages to keep in the overlay (and
+    /// in the persisted log history). Older messages are dropped once this is
+    /// reached. Defaults to 200.
+    pub fn log_buffer_limit(&self) -> usize {
+        self.log_buffer_limit.unwrap_or(DEFAULT_LOG_BUFFER_LIMIT)
+    }
+
+    /// Returns whether the overlay should log the names of locations as the
+    /// player checks them. Locations checked at the same time are grouped by
+    /// area. Defaults to false.
+    pub fn log_checked_locations(&self) -> bool {
+        self.log_checked_locations
+    }
+
+    /// Returns the minimum time between granting two trap items, so that a
+    /// backlog of traps received while offline doesn't all go off at once.
+    /// Defaults to 30 seconds.
+    pub fn trap_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.trap_interval_secs
+                .unwrap_or(DEFAULT_TRAP_INTERVAL_SECS),
+        )
+    }
+
+    /// Returns how long to wait for a connection to the server to be fully
+    /// established before giving up on it. Defaults to 15 seconds.
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(
+            self.connect_timeout_secs
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        )
+    }
+
+    /// Returns the minimum time between granting two items when there isn't a
+    /// large backlog. Defaults to one second.
+    pub fn item_grant_interval(&self) -> Duration {
+        Duration::from_millis(
+            self.item_grant_interval_ms
+                .unwrap_or(DEFAULT_ITEM_GRANT_INTERVAL_MS),
+        )
+    }
+
+    /// Returns the grace period after either sending or receiving a death link
+    /// during which no further death links will be sent or received. Defaults
+    /// to 30 seconds.
+    pub fn death_link_grace_period(&self) -> Duration {
+        Duration::from_secs(
+            self.death_link_grace_period_secs
+                .unwrap_or(DEFAULT_DEATH_LINK_GRACE_PERIOD_SECS),
+        )
+    }
+
+    /// Sets the death link grace period in the config file.
+    pub fn set_death_link_grace_period(&mut self, period: Duration) {
+        self.death_link_grace_period_secs = Some(period.as_secs());
+    }
+
+    /// Returns whether to send death links when the player dies, if the slot
+    /// has death link enabled. Defaults to true.
+    pub fn send_death_links(&self) -> bool {
+        self.send_death_links.unwrap_or(true)
+    }
+
+    /// Sets whether to send death links in the config file.
+    pub fn set_send_death_links(&mut self, send: bool) {
+        self.send_death_links = Some(send);
+    }
+
+    /// Returns whether to kill the player when a death link is received, if
+    /// the slot has death link enabled. Defaults to true.
+    pub fn receive_death_links(&self) -> bool {
+        self.receive_death_links.unwrap_or(true)
+    }
+
+    /// Sets whether to receive death links in the config file.
+    pub fn set_receive_death_links(&mut self, receive: bool) {
+        self.receive_death_links = Some(receive);
+    }
+
+    /// Returns whether to automatically send `!release` to the server when
+    /// the player achieves their goal. Defaults to false.
+    pub fn release_on_goal(&self) -> bool {
+        self.release_on_goal
+    }
+
+    /// Returns whether to automatically send `!collect` to the server when
+    /// the player achieves their goal. Defaults to false.
+    pub fn collect_on_goal(&self) -> bool {
+        self.collect_on_goal
+    }
+
+    /// Returns whether to play a sound when the player is granted a
+    /// progression item or a trap. Defaults to false.
+    pub fn item_sound_cues(&self) -> bool {
+        self.item_sound_cues
+    }
+
+    /// Sets whether to play item sound cues in the config file.
+    pub fn set_item_sound_cues(&mut self, enabled: bool) {
+        self.item_sound_cues = enabled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_url_accepts_host_and_port() {
+        assert_eq!(
+            validate_url("archipelago.gg:12345").unwrap(),
+            "archipelago.gg:12345"
+        );
+        assert_eq!(validate_url("localhost:38281").unwrap(), "localhost:38281");
+    }
+
+    #[test]
+    fn validate_url_accepts_websocket_schemes() {
+        assert_eq!(
+            validate_url("ws://localhost:38281").unwrap(),
+            "ws://localhost:38281"
+        );
+        assert_eq!(
+            validate_url("wss://archipelago.gg:12345").unwrap(),
+            "wss://archipelago.gg:12345"
+        );
+    }
+
+    #[test]
+    fn validate_url_trims_whitespace_and_slashes() {
+        assert_eq!(
+            validate_url("  archipelago.gg:12345/ ").unwrap(),
+            "archipelago.gg:12345"
+        );
+    }
+
+    #[test]
+    fn validate_url_rejects_other_schemes() {
+        assert!(validate_url("http://archipelago.gg:12345").is_err());
+    }
+
+    #[test]
+    fn validate_url_rejects_missing_parts() {
+        assert!(validate_url("").is_err());
+        assert!(validate_url("wss://").is_err());
+        assert!(validate_url("archipelago.gg").is_err());
+        assert!(validate_url("archipelago.gg:").is_err());
+        assert!(validate_url(":12345").is_err());
+    }
+
+    #[test]
+    fn validate_url_rejects_invalid_port() {
+        assert!(validate_url("archipelago.gg:port").is_err());
+        assert!(validate_url("archipelago.gg:99999").is_err());
+    }
 }
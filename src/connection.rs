@@ -0,0 +1,70 @@
+use archipelago_rs as ap;
+
+use crate::slot_data::SlotData;
+
+/// A wrapper around [ap::Connection] that may not have an underlying connection
+/// at all, for example because the player cancelled it before it finished
+/// connecting. A missing connection always behaves as disconnected.
+#[derive(Default)]
+pub struct Connection(Option<ap::Connection<SlotData>>);
+
+impl Connection {
+    /// Returns a connection with no underlying connection.
+    pub fn none() -> Self {
+        Default::default()
+    }
+
+    /// Returns the current connection type.
+    pub fn state_type(&self) -> ap::ConnectionStateType {
+        self.0
+            .as_ref()
+            .map_or(ap::ConnectionStateType::Disconnected, |c| c.state_type())
+    }
+
+    /// Returns whether the current connection is disconnected.
+    pub fn is_disconnected(&self) -> bool {
+        self.0.as_ref().is_none_or(|c| c.is_disconnected())
+    }
+
+    /// Returns a reference to the Archipelago client, if it's connected.
+    pub fn client(&self) -> Option<&ap::Client<SlotData>> {
+        self.0.as_ref()?.client()
+    }
+
+    /// Returns a mutable reference to the Archipelago client, if it's
+    /// connected.
+    pub fn client_mut(&mut self) -> Option<&mut ap::Client<SlotData>> {
+        self.0.as_mut()?.client_mut()
+    }
+
+    /// Updates the underlying connection and returns any new events.
+    pub fn update(&mut self) -> Vec<ap::Event> {
+        self.0.as_mut().map(|c| c.update()).unwrap_or_default()
+    }
+
+    /// Returns the error that caused the connection to disconnect, or None if
+    /// it hasn't disconnected or there was never an underlying connection.
+    pub fn error(&self) -> Option<&ap::Error> {
+        self.0
+            .as_ref()
+            .filter(|c| c.is_disconnected())
+            .map(|c| c.err())
+    }
+
+    /// Returns the error that caused the connection to disconnect.
+    ///
+    /// Like [ap::Connection::err], this panics if the connection hasn't
+    /// encountered an error.
+    pub fn err(&self) -> &ap::Error {
+        self.0
+            .as_ref()
+            .expect("a connection that was never started has no error")
+            .err()
+    }
+}
+
+impl From<ap::Connection<SlotData>> for Connection {
+    fn from(connection: ap::Connection<SlotData>) -> Self {
+        Self(Some(connection))
+    }
+}
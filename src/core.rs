@@ -1,23 +1,21 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::{Duration, Instant, SystemTime};
-use std::{io, iter::ExactSizeIterator, mem};
+use std::{fmt, io, iter::ExactSizeIterator, mem};
+use std::{fs, path::PathBuf};
 
 use anyhow::{Error, Result, bail};
 use archipelago_rs as ap;
+use chrono::Local;
 use darksouls3::{app_menu::*, cs::*, param::*, sprj::*};
 use fromsoftware_shared::{FromStatic, InstanceResult, Superclass};
 use log::*;
 
-use crate::item::{EquipParamExt, ItemIdExt};
-use crate::slot_data::{DeathLinkOption, I64Key, SlotData};
-use crate::{config::Config, save_data::*};
-
-/// The maximum number of log messages to store.
-///
-/// This is relatively low because imgui is not very efficient about not
-/// rendering the offscreen messages every frame, which can cause real slowdown
-/// over long runs with chatty connections.
-const LOG_BUFFER_LIMIT: usize = 200;
+use crate::client::ArchipelagoClient;
+use crate::connection::Connection;
+use crate::item::{EquipParamExt, ItemIdExt, PATH_OF_THE_DRAGON_GESTURE};
+use crate::log_history::LogHistory;
+use crate::slot_data::{DeathLinkOption, I64Key, SlotData, default_goal};
+use crate::{config::Config, log_file, save_data::*, sound, utils};
 
 /// The core of the Archipelago mod. This is responsible for running the
 /// non-UI-related game logic and interacting with the Archieplago client.
@@ -31,8 +29,56 @@ pub struct Core {
     /// The log of prints displayed in the overlay.
     log_buffer: VecDeque<ap::Print>,
 
+    /// The total number of prints that have been added to [log_buffer],
+    /// including those that have since been dropped to keep it within
+    /// [Config::log_buffer_limit].
+    log_count: usize,
+
+    /// The persistent record of [log_buffer], or None if the user hasn't
+    /// enabled it.
+    log_history: Option<LogHistory>,
+
     /// The Archipelago client connection.
-    connection: ap::Connection<SlotData>,
+    connection: Connection,
+
+    /// The time at which [connection] was created. Used to show how long a
+    /// connection attempt has been running.
+    connection_start_time: Instant,
+
+    /// The state of [connection] as of the last time [sync_connection_state]
+    /// ran. Used to detect transitions between states.
+    last_state: ap::ConnectionStateType,
+
+    /// Callbacks to run whenever [connection] changes state. See
+    /// [add_connection_state_listener].
+    connection_state_listeners: Vec<ConnectionStateListener>,
+
+    /// The URL that [connection] is using. This differs from the config's URL
+    /// in that it has an explicit scheme if [Config::scheme_fallback] is
+    /// enabled, which may have been switched by [try_scheme_fallback].
+    connection_url: String,
+
+    /// Whether we're waiting for the player to load a save before connecting,
+    /// because the config has [Config::connect_on_load] set.
+    waiting_for_save: bool,
+
+    /// Whether `apconfig.json` didn't exist when the mod started, so the
+    /// player needs to enter their connection information before the client
+    /// can connect. See [set_up_connection].
+    needs_setup: bool,
+
+    /// The number of times in a row the client has automatically tried to
+    /// reconnect since the connection dropped. Reset once a connection
+    /// succeeds or the player takes over.
+    reconnect_attempts: usize,
+
+    /// The time at which the client will next automatically try to reconnect,
+    /// or None if it's not planning to.
+    next_reconnect_time: Option<Instant>,
+
+    /// Whether [connection] was created by [try_scheme_fallback]. Used to
+    /// ensure we only fall back once per connection attempt.
+    used_scheme_fallback: bool,
 
     /// Events we're waiting to process until the player loads a save. This is
     /// always empty unless a connection is connected and the player is on the
@@ -40,29 +86,88 @@ pub struct Core {
     event_buffer: Vec<ap::Event>,
 
     /// The time we last granted an item to the player. Used to ensure we don't
-    /// give more than one item per second.
+    /// give items faster than [item_grant_interval] allows.
     last_item_time: Instant,
 
+    /// Whether the client is granting items quickly to clear a backlog of more
+    /// than [CATCH_UP_THRESHOLD] items. This stays on until the backlog is
+    /// completely cleared.
+    catching_up: bool,
+
+    /// Items that have been granted since the last call to
+    /// [drain_granted_items].
+    granted_items: Vec<GrantedItem>,
+
+    /// The index of the first item that shouldn't be delivered yet, or None if
+    /// all items should be delivered. This is only set by a debugging command,
+    /// to pause delivery at a particular point for inspection.
+    item_index_ceiling: Option<usize>,
+
+    /// The time we last granted a trap item to the player, or None if we
+    /// haven't granted any this session. Traps are throttled separately (and
+    /// more strictly) than other items according to [Config::trap_interval].
+    last_trap_time: Option<Instant>,
+
     /// The time at which we noticed the game loading (as indicated by
     /// MapItemMan coming into existence). Used to compute the grace period
     /// before we start doing stuff in game. None if the game is not currently
     /// loaded.
     load_time: Option<Instant>,
 
-    /// The number of locations sent to the server in this session. This always
-    /// starts at 0 when the player boots the game again to ensure that they
-    /// resend any locations that may have been missed.
-    locations_sent: usize,
+    /// Whether any items have been granted to the player since the DLL was
+    /// loaded. Used to distinguish the first load of the session (which needs
+    /// the full [GRACE_PERIOD]) from subsequent reloads.
+    granted_this_session: bool,
+
+    /// Sends the locations the player has checked to the server.
+    location_sender: LocationSender,
+
+    /// The time the server most recently took to acknowledge location checks,
+    /// or None if it hasn't acknowledged any over the current connection.
+    check_confirmation_time: Option<Duration>,
 
     /// The set of DS3 item IDs for shop locations whose hints have already been
     /// sent to the server. This is intentionally not preserved across loads so
     /// that if something goes wrong, the player can quit out and re-send hints.
     shop_items_hinted: HashSet<ItemId>,
 
+    /// A map from player names to the number of clients connected to the
+    /// server for that player. This is tracked using the server's join and
+    /// part messages, so players who haven't joined or left since this client
+    /// connected aren't included.
+    online_players: HashMap<String, usize>,
+
+    /// Hints involving this player that have been received this session, from
+    /// oldest to newest. Unlike [log_buffer], these are never dropped, and each
+    /// location only appears once with its most recent status.
+    hints: Vec<ap::Print>,
+
+    /// The hints the server has sent for locations in this player's world,
+    /// keyed by location ID. These are requested with [scout_locations], and
+    /// record what each location contains and who it's for.
+    scouted_locations: HashMap<i64, ap::Print>,
+
+    /// The number of death links received and ignored during the current
+    /// death link grace period.
+    ignored_death_links: usize,
+
+    /// Whether the player has temporarily paused death link for this session.
+    /// While this is set, death links are neither sent nor received, but the
+    /// connection keeps its DeathLink tag so resuming is instant.
+    death_link_paused: bool,
+
+    /// Tracks how long the player has appeared to be dead, so that transient
+    /// zero-HP readings don't send death links.
+    death_confirmation: DeathConfirmation,
+
     /// The last time the player either sent or received a death link (or
     /// started a session).
     last_death_link: Instant,
 
+    /// Statistics about the current connection, logged as a summary when it
+    /// ends.
+    session_stats: SessionStats,
+
     /// Whether the player has achieved their goal and sent that information to
     /// the Archipelago server. This is stored here rather than in the save data
     /// so that it's resent every time the player starts the game, just in case
@@ -78,43 +183,901 @@ pub struct Core {
 /// to take actions.
 const GRACE_PERIOD: Duration = Duration::from_secs(10);
 
-/// The grace period after either sending or receiving a death link during which
-/// no further death links will be sent or received.
-const DEATH_LINK_GRACE_PERIOD: Duration = Duration::from_secs(30);
+/// The grace period used in place of [GRACE_PERIOD] once items have already
+/// been granted in this session. Reloads (for example after dying) are much
+/// faster than the initial load, so there's no need to make the player wait as
+/// long.
+const RELOAD_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// The number of pending items above which the client starts granting items
+/// at [CATCH_UP_GRANT_INTERVAL] instead of [Config::item_grant_interval].
+const CATCH_UP_THRESHOLD: usize = 10;
+
+/// The minimum time between granting two items while catching up on a large
+/// backlog.
+const CATCH_UP_GRANT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long the player must stay dead before we consider them dead for the
+/// purposes of death link. HP can briefly read as zero during some
+/// transitions, such as revival effects, which shouldn't send death links.
+const DEATH_CONFIRMATION_TIME: Duration = Duration::from_millis(500);
+
+/// The event flag that's set once the player lights the Cemetery of Ash
+/// bonfire. Death links aren't sent or received before this, so that a fresh
+/// character isn't killed before they can even move.
+const FIRST_BONFIRE_FLAG: u32 = 14000001;
+
+/// How long to wait for the server to acknowledge location checks before
+/// sending them again. Checks sent just as the connection dies can otherwise be
+/// silently lost until the next reconnect.
+const LOCATION_RESEND_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The number of times to resend unacknowledged locations before giving up
+/// until the next connection. Each resend waits twice as long as the last.
+const MAX_LOCATION_RESENDS: u32 = 4;
+
+/// How long to wait before each successive attempt to automatically reconnect
+/// after the connection drops. Attempts beyond the end of this list use the
+/// last delay.
+const RECONNECT_DELAYS: &[Duration] = &[
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+];
+
+/// The number of times in a row the client will automatically try to reconnect
+/// before giving up and waiting for the player to reconnect manually. With
+/// [RECONNECT_DELAYS], this keeps trying for about five minutes.
+const MAX_RECONNECT_ATTEMPTS: usize = 12;
+
+/// Why a connection ended, as far as the client can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DisconnectKind {
+    /// The server cleanly closed a connection that had been established. This
+    /// happens when the server restarts or the room shuts down or idles out,
+    /// as well as when an administrator disconnects the player. The protocol
+    /// doesn't say which, so this shouldn't be reported as any one of them.
+    ClosedByServer,
+
+    /// The connection failed or dropped because of an error.
+    Failed,
+}
+
+/// Classifies the fatal error [err] that ended a connection which was in
+/// [state] when it ended.
+fn classify_disconnect(state: ap::ConnectionStateType, err: &ap::Error) -> DisconnectKind {
+    // Network drops show up as IO errors rather than clean closes.
+    if state == ap::ConnectionStateType::Connected
+        && matches!(
+            err,
+            ap::Error::WebSocket(tungstenite::Error::ConnectionClosed)
+        )
+    {
+        DisconnectKind::ClosedByServer
+    } else {
+        DisconnectKind::Failed
+    }
+}
+
+/// Returns whether [err] might go away on its own, so it's worth automatically
+/// retrying the connection. Errors that mean the player's configuration is
+/// wrong, like an incorrect slot name or password, will never succeed no
+/// matter how many times we retry.
+fn is_retryable_connection_error(err: &ap::Error) -> bool {
+    // The server only refuses connections because of the connection's
+    // details, which won't change on their own.
+    if matches!(err, ap::Error::ConnectionRefused(_)) {
+        return false;
+    }
+
+    !matches!(
+        err,
+        ap::Error::WebSocket(tungstenite::Error::Url(_) | tungstenite::Error::Tls(_))
+    )
+}
+
+/// Returns a user-friendly summary of [err] and advice for fixing it if it's a
+/// common error encountered while setting up a connection, or None otherwise.
+///
+/// The errors returned by the underlying WebSocket library tend to be cryptic,
+/// so this is worth doing for anything players are likely to run into.
+fn describe_connection_error(err: &ap::Error) -> Option<(&'static str, &'static str)> {
+    /// The Windows socket error for an unknown host.
+    const WSAHOST_NOT_FOUND: i32 = 11001;
+
+    /// The Windows socket error for a host with no DNS records.
+    const WSANO_DATA: i32 = 11004;
+
+    // The server reports why it refused a connection using these error codes.
+    // Note that the server allows multiple clients to connect to the same
+    // slot, so there's no "slot taken" error.
+    if let ap::Error::ConnectionRefused(codes) = err {
+        for code in codes {
+            match code.as_str() {
+                "InvalidSlot" => {
+                    return Some((
+                        "Unknown slot name.",
+                        "The slot name in apconfig.json doesn't exist in this room. Re-run \
+                         DS3Randomizer.exe with the correct name.",
+                    ));
+                }
+                "InvalidPassword" => {
+                    return Some((
+                        "Incorrect password.",
+                        "Re-run DS3Randomizer.exe with the room's password.",
+                    ));
+                }
+                "InvalidGame" => {
+                    return Some((
+                        "This slot isn't a Dark Souls III world.",
+                        "Make sure the slot name in apconfig.json is for your DS3 world.",
+                    ));
+                }
+                "IncompatibleVersion" => {
+                    return Some((
+                        "Incompatible server version.",
+                        "The server is running a version of Archipelago this client doesn't \
+                         support.",
+                    ));
+                }
+                _ => {}
+            }
+        }
+        return None;
+    }
+
+    let ap::Error::WebSocket(err) = err else {
+        return None;
+    };
+    match err {
+        tungstenite::Error::Io(io) if io.kind() == io::ErrorKind::ConnectionRefused => Some((
+            "Connection refused.",
+            "Make sure the server session is running and the URL is up-to-date.",
+        )),
+        tungstenite::Error::Io(io)
+            if matches!(io.raw_os_error(), Some(WSAHOST_NOT_FOUND | WSANO_DATA)) =>
+        {
+            Some((
+                "Couldn't find the server.",
+                "Check that the URL's hostname is spelled correctly and that you're \
+                 connected to the internet.",
+            ))
+        }
+        tungstenite::Error::Io(io) if io.kind() == io::ErrorKind::TimedOut => Some((
+            "Connection timed out.",
+            "Make sure the server session is running and that your internet connection \
+             is working.",
+        )),
+        tungstenite::Error::Url(_) => Some((
+            "Invalid server URL.",
+            "The URL should look like \"archipelago.gg:12345\". Use \"Change URL\" to fix \
+             it.",
+        )),
+        tungstenite::Error::Tls(_) => Some((
+            "Secure connection failed.",
+            "The server may not support secure connections. Try connecting with a \
+             \"ws://\" URL instead.",
+        )),
+        _ => None,
+    }
+}
+
+/// Returns [url] with an explicit WebSocket scheme, defaulting to secure
+/// WebSockets if it doesn't have one, so that [Core::try_scheme_fallback] knows
+/// which scheme to switch to.
+///
+/// If [scheme_fallback] is false, [url] is returned as-is so that the
+/// underlying library chooses the scheme just as it did before scheme fallback
+/// existed.
+fn with_scheme(url: &str, scheme_fallback: bool) -> String {
+    if !scheme_fallback || url.contains("://") {
+        url.to_string()
+    } else {
+        format!("wss://{url}")
+    }
+}
+
+/// Returns whether [url] and [password] are the ones [config] already uses and
+/// the connection is still alive, so there's no need to reconnect. An empty
+/// password is the same as no password.
+fn is_current_connection(
+    config: &Config,
+    url: &str,
+    password: Option<&str>,
+    disconnected: bool,
+) -> bool {
+    url == config.url()
+        && password.filter(|password| !password.is_empty()) == config.password()
+        && !disconnected
+}
+
+/// A callback registered with [Core::add_connection_state_listener].
+type ConnectionStateListener =
+    Box<dyn FnMut(&mut Core, ap::ConnectionStateType, ap::ConnectionStateType) + Send>;
+
+/// Tracks whether the player has stayed dead for [DEATH_CONFIRMATION_TIME].
+#[derive(Default)]
+struct DeathConfirmation {
+    /// The time at which the player was first seen dead, or None if they're
+    /// currently alive.
+    dead_since: Option<Instant>,
+}
+
+impl DeathConfirmation {
+    /// Records whether the player appeared dead at [now], and returns whether
+    /// they've been dead continuously for at least [DEATH_CONFIRMATION_TIME].
+    fn update(&mut self, dead: bool, now: Instant) -> bool {
+        if !dead {
+            self.dead_since = None;
+            return false;
+        }
+        now.saturating_duration_since(*self.dead_since.get_or_insert(now))
+            >= DEATH_CONFIRMATION_TIME
+    }
+}
+
+/// Sends the locations the player has checked to the server, resending ones
+/// that it hasn't acknowledged.
+#[derive(Default)]
+struct LocationSender {
+    /// The locations sent to the server over the current connection. Only
+    /// locations that aren't in this set are sent when the player checks new
+    /// locations. This is cleared whenever a new connection is established to
+    /// ensure that any locations that may have been missed are resent.
+    sent: HashSet<i64>,
+
+    /// The last time locations were sent to the server, or None if none have
+    /// been sent over the current connection. Used to resend locations that
+    /// the server hasn't acknowledged after [LOCATION_RESEND_INTERVAL].
+    last_send_time: Option<Instant>,
+
+    /// The number of times unacknowledged locations have been resent since
+    /// locations were last sent for the first time. Resends back off based on
+    /// this and stop after [MAX_LOCATION_RESENDS].
+    resends: u32,
+
+    /// The time at which we most recently sent location checks, or None if
+    /// the server has acknowledged every check. This restarts with each resend
+    /// so that the time spent backing off isn't counted as the server's.
+    round_trip_start: Option<Instant>,
+}
+
+impl LocationSender {
+    /// Forgets everything sent over the previous connection.
+    fn reset(&mut self) {
+        *self = Default::default();
+    }
+
+    /// Records which of the locations in [save] [client] has acknowledged and
+    /// sends any that are new or overdue for a resend as of [now].
+    ///
+    /// If this finds that the server has acknowledged every outstanding check,
+    /// returns how long that took since they were last sent.
+    fn sync(
+        &mut self,
+        client: &mut impl ArchipelagoClient,
+        save: &mut SaveData,
+        now: Instant,
+    ) -> Result<Option<Duration>> {
+        let unacknowledged = unacknowledged_locations(client, save);
+        let round_trip = if unacknowledged.is_empty() {
+            self.round_trip_start
+                .take()
+                .map(|start| now.saturating_duration_since(start))
+        } else {
+            None
+        };
+
+        let resend_interval = LOCATION_RESEND_INTERVAL * 2u32.pow(self.resends);
+        let resend = !unacknowledged.is_empty()
+            && self.resends < MAX_LOCATION_RESENDS
+            && self
+                .last_send_time
+                .is_some_and(|time| now.saturating_duration_since(time) >= resend_interval);
+        let to_send = if resend {
+            self.resends += 1;
+            info!(
+                "Resending {} unacknowledged locations (attempt {}/{})",
+                unacknowledged.len(),
+                self.resends,
+                MAX_LOCATION_RESENDS
+            );
+            if self.resends == MAX_LOCATION_RESENDS {
+                info!("Not resending locations again until the next connection");
+            }
+            unacknowledged
+        } else {
+            let new = unacknowledged
+                .into_iter()
+                .filter(|location| !self.sent.contains(location))
+                .collect::<Vec<_>>();
+            if !new.is_empty() {
+                self.resends = 0;
+            }
+            new
+        };
+
+        if !to_send.is_empty() {
+            self.round_trip_start = Some(now);
+            self.sent.extend(&to_send);
+            client.send_location_checks(to_send)?;
+            self.last_send_time = Some(now);
+        }
+        Ok(round_trip)
+    }
+}
+
+/// Records which of the locations the player has checked in [save] [client]
+/// considers checked, and returns the ones it has yet to acknowledge.
+///
+/// Locations that the server considers neither checked nor missing, such as
+/// ones from a different world, will never be acknowledged, so they're left
+/// out.
+fn unacknowledged_locations(client: &impl ArchipelagoClient, save: &mut SaveData) -> Vec<i64> {
+    save.acknowledged_locations.extend(
+        save.locations
+            .iter()
+            .filter(|location| client.is_checked(**location)),
+    );
+    save.locations
+        .difference(&save.acknowledged_locations)
+        .filter(|location| client.is_missing(**location))
+        .copied()
+        .collect()
+}
+
+/// The result of reporting one of the player's deaths with [report_death].
+#[derive(Debug, PartialEq, Eq)]
+enum DeathReport {
+    /// A death link was sent to the other players.
+    Sent,
+
+    /// The death was absorbed by death link amnesty, with [remaining] more
+    /// deaths allowed before one is sent.
+    Amnesty { remaining: u8 },
+}
+
+/// Counts one of the player's deaths against [amnesty] in [save], and sends a
+/// death link through [client] once the player has died that many times.
+fn report_death(
+    client: &mut impl ArchipelagoClient,
+    save: &mut SaveData,
+    amnesty: u8,
+) -> Result<DeathReport> {
+    save.deaths += 1;
+    if save.deaths < amnesty {
+        return Ok(DeathReport::Amnesty {
+            remaining: amnesty - save.deaths,
+        });
+    }
+
+    // The game doesn't keep track of what killed the player in a way we can
+    // access, so this can't name the killer.
+    let cause = format!("{} died.", client.player_name());
+    client.send_death_link(cause)?;
+    save.deaths = 0;
+    Ok(DeathReport::Sent)
+}
+
+/// Tells the server through [client] that the player has achieved their goal,
+/// and returns whether this is the first time they've done so with [save].
+///
+/// The goal status is resent every session, but anything else that happens on
+/// goal should only happen the first time the player reaches it.
+fn send_goal(client: &mut impl ArchipelagoClient, save: Option<&mut SaveData>) -> Result<bool> {
+    client.send_goal()?;
+    Ok(save.is_some_and(|save| !mem::replace(&mut save.announced_goal, true)))
+}
+
+/// Returns the number of items [client] has received that haven't yet been
+/// granted to the player in [save], including deferred traps.
+fn pending_items(client: &impl ArchipelagoClient, save: &SaveData) -> usize {
+    client
+        .received_item_count()
+        .saturating_sub(save.items_granted)
+        + save.deferred_traps.len()
+}
+
+/// The times that limit how soon the next item can be granted.
+#[derive(Clone, Copy)]
+struct GrantTimes {
+    /// The time at which the last item was granted.
+    last_item: Instant,
+
+    /// The time at which the last trap was granted, or None if none has been
+    /// granted this session.
+    last_trap: Option<Instant>,
+
+    /// The minimum time between granting any two items.
+    item_interval: Duration,
+
+    /// The minimum time between granting two traps.
+    trap_interval: Duration,
+}
+
+/// What to do next with the items the server has sent, as decided by
+/// [next_item_action].
+#[derive(Debug, PartialEq, Eq)]
+enum ItemAction {
+    /// Nothing should be granted yet.
+    Wait,
+
+    /// Grant the item with this index, which is the next one in order.
+    Grant(usize),
+
+    /// Grant the trap with this index, which was deferred earlier.
+    GrantDeferred(usize),
+
+    /// Set aside the trap with this index, which is the next one in order,
+    /// until the trap interval has passed so it doesn't hold back the items
+    /// after it.
+    Defer(usize),
+}
+
+/// Decides which received item to handle at [now], given that the player has
+/// been granted items up to [items_granted] apart from [deferred_traps].
+///
+/// [is_trap] returns whether the received item with a given index is a trap,
+/// or None if no such item has been received. Items at or after [ceiling] are
+/// held back, although deferred traps are still granted.
+fn next_item_action(
+    items_granted: usize,
+    deferred_traps: &[usize],
+    ceiling: Option<usize>,
+    is_trap: impl Fn(usize) -> Option<bool>,
+    times: GrantTimes,
+    now: Instant,
+) -> ItemAction {
+    if now.saturating_duration_since(times.last_item) < times.item_interval {
+        return ItemAction::Wait;
+    }
+
+    // Grant a trap that was deferred earlier as soon as the trap interval
+    // allows, before moving on to newer items.
+    let trap_ready = times
+        .last_trap
+        .is_none_or(|time| now.saturating_duration_since(time) >= times.trap_interval);
+    if trap_ready
+        && let Some(&index) = deferred_traps.first()
+        && is_trap(index).is_some()
+    {
+        return ItemAction::GrantDeferred(index);
+    }
+
+    if ceiling.is_some_and(|ceiling| items_granted >= ceiling) {
+        return ItemAction::Wait;
+    }
+    match is_trap(items_granted) {
+        None => ItemAction::Wait,
+        Some(true) if !trap_ready => ItemAction::Defer(items_granted),
+        Some(_) => ItemAction::Grant(items_granted),
+    }
+}
+
+/// Returns whether [save] is definitively associated with the seed [client] is
+/// connected to. This is false if the save doesn't have a seed yet.
+///
+/// Items must never be granted unless this is true. [Core::check_seed_conflict]
+/// should already prevent that, but granting items to the wrong save can't be
+/// undone so we double-check right before granting.
+fn seed_matches(client: &impl ArchipelagoClient, save: &SaveData) -> bool {
+    save.seed.as_deref() == Some(client.seed_name())
+}
+
+/// Returns an error if there's a conflict between the seed of the room the
+/// player is connected to, the seed recorded in their save, and the seed
+/// [Config] was generated for.
+///
+/// Either of the first two may be None if they aren't known yet.
+fn seed_conflict(
+    client_seed: Option<&str>,
+    save_seed: Option<&str>,
+    config_seed: &str,
+) -> Result<(), SeedConflict> {
+    match (client_seed, save_seed) {
+        (Some(client_seed), _) if client_seed != config_seed => Err(SeedConflict(format!(
+            "You've connected to a different Archipelago multiworld than the one that \
+             DS3Randomizer.exe used!\n\
+             \n\
+		 Connected room seed: {}\n\
+             DS3Randomizer.exe seed: {}",
+            client_seed, config_seed
+        ))),
+        (Some(client_seed), Some(save_seed)) if client_seed != save_seed => {
+            Err(SeedConflict(format!(
+                "You've connected to a different Archipelago multiworld than the one that \
+             you used before with this save!\n\
+             \n\
+		 Connected room seed: {}\n\
+		 Save file seed: {}",
+                client_seed, save_seed
+            )))
+        }
+        (_, Some(save_seed)) if config_seed != save_seed => Err(SeedConflict(format!(
+            "Your most recent DS3Randomizer.exe invocation connected to a different \
+             Archipealgo multiworld than the one that you used before with this save!\n\
+             \n\
+             DS3Randomizer.exe seed: {}\n\
+             Save file seed: {}",
+            config_seed, save_seed
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Statistics about a single connection to the Archipelago server.
+#[derive(Default)]
+struct SessionStats {
+    /// The time at which the connection was established, or None if there
+    /// isn't an active session.
+    start_time: Option<Instant>,
+
+    /// The number of items granted to the player.
+    items_received: usize,
+
+    /// The number of new locations the player checked.
+    locations_checked: usize,
+
+    /// The number of death links sent to other players.
+    death_links_sent: usize,
+
+    /// The number of death links that killed the player.
+    death_links_received: usize,
+}
+
+/// The classification of an Archipelago item, which determines how it's
+/// highlighted in the overlay.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ItemClass {
+    Progression,
+    Useful,
+    Trap,
+    Filler,
+}
+
+impl ItemClass {
+    /// Returns the classification of [item]. Items with multiple
+    /// classifications use the most important one.
+    pub fn of(item: &ap::Item) -> Self {
+        if item.is_progression() {
+            ItemClass::Progression
+        } else if item.is_useful() {
+            ItemClass::Useful
+        } else if item.is_trap() {
+            ItemClass::Trap
+        } else {
+            ItemClass::Filler
+        }
+    }
+}
+
+/// A special effect that an item can apply when it's received, as requested by
+/// [SlotData::item_effects].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemEffect {
+    /// Restores the player's HP to its maximum.
+    FullHeal,
+
+    /// Kills the player, as for a death link.
+    Kill,
+
+    /// Warps the player to the last bonfire they rested at.
+    WarpToBonfire,
+}
+
+impl ItemEffect {
+    /// Returns the effect named [name] in slot data, or None if there's no
+    /// such effect.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "full_heal" => Some(ItemEffect::FullHeal),
+            "kill" => Some(ItemEffect::Kill),
+            "warp_to_bonfire" => Some(ItemEffect::WarpToBonfire),
+            _ => None,
+        }
+    }
+}
+
+/// How to handle an item received from the server, as decided by
+/// [dispatch_item].
+#[derive(Debug, PartialEq, Eq)]
+struct ItemDispatch {
+    /// The effect to apply, if any.
+    effect: Option<ItemEffect>,
+
+    /// Whether to grant the item's DS3 equivalent.
+    grant: bool,
+}
+
+/// Decides how to handle the Archipelago item [ap_id] based on [slot_data].
+///
+/// Items with effects don't need to have a DS3 equivalent, so they're only
+/// granted if they have one. Effects this client doesn't know about are logged
+/// and skipped.
+fn dispatch_item(slot_data: &SlotData, ap_id: i64) -> ItemDispatch {
+    let id_key = I64Key(ap_id);
+    let Some(name) = slot_data.item_effects.get(&id_key) else {
+        return ItemDispatch {
+            effect: None,
+            grant: true,
+        };
+    };
+
+    let effect = ItemEffect::from_name(name);
+    if effect.is_none() {
+        warn!("Skipping unknown item effect {} for AP ID {}", name, ap_id);
+    }
+    ItemDispatch {
+        effect,
+        grant: slot_data.ap_ids_to_item_ids.contains_key(&id_key),
+    }
+}
+
+/// An item that was just granted to the player from the Archipelago server.
+pub struct GrantedItem {
+    /// The Archipelago name of the item.
+    pub name: String,
+
+    /// The item's classification.
+    pub class: ItemClass,
+
+    /// The name of the player who sent the item.
+    pub sender: String,
+}
+
+/// A fatal error indicating that the server, the save, and the config disagree
+/// about which seed is in use.
+///
+/// Unlike most fatal errors, this can be resolved without restarting the game
+/// by loading a different save, so it's surfaced as a distinct type to allow
+/// the UI to offer [Core::recheck_seed_conflict].
+#[derive(Debug)]
+pub struct SeedConflict(String);
+
+impl fmt::Display for SeedConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SeedConflict {}
 
 impl Core {
     /// Creates a new instance of the mod.
     pub fn new() -> Result<Self> {
-        let config = Config::load()?;
-        let connection = Self::new_connection(&config);
-        Ok(Self {
+        let (config, needs_setup) = match Config::load()? {
+            Some(config) => (config, false),
+            None => (Config::default(), true),
+        };
+        let connection_url = with_scheme(config.url(), config.scheme_fallback());
+        let waiting_for_save = config.connect_on_load();
+        let connection = if waiting_for_save || needs_setup {
+            Connection::none()
+        } else {
+            Self::new_connection(&config, &connection_url)
+        };
+
+        let mut log_buffer = VecDeque::new();
+        let log_history = if config.persist_logs() {
+            match LogHistory::load(config.log_buffer_limit()) {
+                Ok((history, entries)) => {
+                    // Display messages from previous sessions in gray so
+                    // they're distinct from live messages.
+                    log_buffer.extend(entries.into_iter().map(|entry| {
+                        ap::Print::from(ap::RichText::Color {
+                            text: format!("[{}] {}", entry.time, entry.text),
+                            color: ap::TextColor::Black,
+                        })
+                    }));
+                    Some(history)
+                }
+                Err(err) => {
+                    warn!("Failed to load overlay log history: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Let the player know where to find their logs if they couldn't be
+        // written to the usual place.
+        let default_log_dir = utils::mod_directory().ok().map(|dir| dir.join("log"));
+        match log_file::root() {
+            Some(dir) if Some(&dir) != default_log_dir.as_ref() => {
+                log_buffer.push_back(ap::Print::from(ap::RichText::Color {
+                    text: format!(
+                        "Couldn't write logs to the mod directory. Logging to {} instead.",
+                        dir.to_string_lossy()
+                    ),
+                    color: ap::TextColor::Yellow,
+                }))
+            }
+            Some(_) => {}
+            None => log_buffer.push_back(ap::Print::from(ap::RichText::Color {
+                text: "Couldn't open a log file. Logs won't be saved.".into(),
+                color: ap::TextColor::Yellow,
+            })),
+        }
+
+        if needs_setup {
+            log_buffer.push_back(ap::Print::from(ap::RichText::Color {
+                text: "apconfig.json doesn't exist. Have you run \
+                       randomizer\\DS3Randomizer.exe? If you have, click \"Set Up\" \
+                       to enter your connection information manually."
+                    .into(),
+                color: ap::TextColor::Yellow,
+            }));
+        }
+
+        let mut core = Self {
             config,
+            last_state: connection.state_type(),
+            connection_state_listeners: vec![],
             connection,
+            connection_start_time: Instant::now(),
+            connection_url,
+            waiting_for_save,
+            needs_setup,
+            reconnect_attempts: 0,
+            next_reconnect_time: None,
+            used_scheme_fallback: false,
             event_buffer: vec![],
-            log_buffer: Default::default(),
+            log_count: log_buffer.len(),
+            log_buffer,
+            log_history,
             last_item_time: Instant::now(),
+            catching_up: false,
+            granted_items: vec![],
+            last_trap_time: None,
+            item_index_ceiling: None,
             load_time: None,
-            locations_sent: 0,
+            granted_this_session: false,
+            location_sender: Default::default(),
+            check_confirmation_time: None,
+            session_stats: Default::default(),
             shop_items_hinted: Default::default(),
+            online_players: Default::default(),
+            hints: vec![],
+            scouted_locations: Default::default(),
+            ignored_death_links: 0,
+            death_link_paused: false,
+            death_confirmation: Default::default(),
             last_death_link: Instant::now(),
             sent_goal: false,
             error: None,
-        })
+        };
+
+        core.add_connection_state_listener(|core, old, new| {
+            if new == ap::ConnectionStateType::Disconnected {
+                core.on_disconnect(old);
+            }
+        });
+        core.add_connection_state_listener(|core, old, _| {
+            if old == ap::ConnectionStateType::Connected {
+                core.log_session_summary();
+            }
+        });
+        Ok(core)
     }
 
-    /// Creates a new [ClientConnection] based on the connection information in [config].
-    fn new_connection(config: &Config) -> ap::Connection<SlotData> {
+    /// Creates a new [Connection] to [url] based on the connection information
+    /// in [config].
+    fn new_connection(config: &Config, url: &str) -> Connection {
         let mut options = ap::ConnectionOptions::new()
             .receive_items(ap::ItemHandling::OtherWorlds {
                 own_world: false,
                 starting_inventory: true,
             })
-            .tags(vec!["DeathLink"]);
+            .tags(Self::connection_tags(config));
         if let Some(password) = config.password() {
             options = options.password(password);
         }
 
-        ap::Connection::new(config.url(), "Dark Souls III", config.slot(), options)
+        ap::Connection::new(url, "Dark Souls III", config.slot(), options).into()
+    }
+
+    /// Returns the tags to send to the server, combining those the client
+    /// manages with any extras from [config].
+    ///
+    /// These are only used for new connections. The client never sends a
+    /// ConnectUpdate, so an existing connection keeps the tags it started with.
+    fn connection_tags(config: &Config) -> Vec<&str> {
+        /// Tags that would make the server treat this as something other than
+        /// a full game client, which would break item and location handling.
+        const FORBIDDEN_TAGS: &[&str] = &["TextOnly", "Tracker", "HintGame"];
+
+        let mut tags = vec!["DeathLink"];
+        for tag in config.tags() {
+            if FORBIDDEN_TAGS.contains(&tag.as_str()) {
+                warn!("Ignoring tag {tag} from config, since this is a game client");
+            } else if !tags.contains(&tag.as_str()) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    /// Replaces [connection] with a new connection to the URL in [config].
+    fn connect(&mut self) {
+        self.waiting_for_save = false;
+        self.connection_url = with_scheme(self.config.url(), self.config.scheme_fallback());
+        self.used_scheme_fallback = false;
+        self.connection = Self::new_connection(&self.config, &self.connection_url);
+        self.connection_start_time = Instant::now();
+    }
+
+    /// Replaces [connection] with a new connection to [connection_url]. Unlike
+    /// [connect], this keeps using whichever scheme last worked rather than
+    /// starting over from the config's URL.
+    fn retry_connection(&mut self) {
+        self.connection = Self::new_connection(&self.config, &self.connection_url);
+        self.connection_start_time = Instant::now();
+    }
+
+    /// Schedules the next automatic reconnection attempt according to
+    /// [RECONNECT_DELAYS], or gives up if there have already been
+    /// [MAX_RECONNECT_ATTEMPTS].
+    fn schedule_reconnect(&mut self) {
+        if self.reconnect_attempts >= MAX_RECONNECT_ATTEMPTS {
+            self.log(format!(
+                "Giving up after {} reconnect attempts. Click \"Reconnect\" to try again.",
+                self.reconnect_attempts
+            ));
+            self.stop_auto_reconnect();
+            return;
+        }
+
+        let delay = RECONNECT_DELAYS[self.reconnect_attempts.min(RECONNECT_DELAYS.len() - 1)];
+        self.next_reconnect_time = Some(Instant::now() + delay);
+        self.log(format!("Reconnecting in {}s...", delay.as_secs()));
+    }
+
+    /// Cancels any pending automatic reconnection, because either a connection
+    /// succeeded or the player has taken over.
+    fn stop_auto_reconnect(&mut self) {
+        self.reconnect_attempts = 0;
+        self.next_reconnect_time = None;
+    }
+
+    /// If [connection] failed to connect in a way that might be fixed by
+    /// switching between secure and insecure WebSockets (and the config allows
+    /// it), starts a new connection using the other scheme.
+    ///
+    /// Returns whether a new connection was started.
+    fn try_scheme_fallback(&mut self) -> bool {
+        if self.used_scheme_fallback || !self.config.scheme_fallback() {
+            return false;
+        }
+
+        let err = self.connection.err();
+        // Most errors we can describe (like a refused connection or an invalid
+        // URL) won't be fixed by changing the scheme, but TLS errors might be.
+        if describe_connection_error(err).is_some()
+            && !matches!(err, ap::Error::WebSocket(tungstenite::Error::Tls(_)))
+        {
+            return false;
+        }
+        let err = err.to_string();
+
+        let url = if let Some(rest) = self.connection_url.strip_prefix("wss://") {
+            format!("ws://{rest}")
+        } else if let Some(rest) = self.connection_url.strip_prefix("ws://") {
+            format!("wss://{rest}")
+        } else {
+            return false;
+        };
+
+        info!(
+            "Connecting to {} failed ({}), trying {}",
+            self.connection_url, err, url
+        );
+        self.connection_url = url;
+        self.used_scheme_fallback = true;
+        self.connection = Self::new_connection(&self.config, &self.connection_url);
+        true
     }
 
     /// Returns the current connection type.
@@ -138,19 +1101,110 @@ impl Core {
             self.log("Reconnecting...");
         }
 
-        self.connection = Self::new_connection(&self.config);
+        self.stop_auto_reconnect();
+        self.connect();
+    }
+
+    /// Returns how long until the client automatically tries to reconnect, or
+    /// None if it's not planning to.
+    pub fn time_until_reconnect(&self) -> Option<Duration> {
+        Some(
+            self.next_reconnect_time?
+                .saturating_duration_since(Instant::now()),
+        )
+    }
+
+    /// Returns whether `apconfig.json` was missing, so the player needs to
+    /// provide their connection information with [set_up_connection] before
+    /// the client can connect.
+    pub fn needs_setup(&self) -> bool {
+        self.needs_setup
+    }
+
+    /// Writes a fresh `apconfig.json` with the given connection information
+    /// and connects to the server.
+    ///
+    /// The seed must match the one DS3Randomizer.exe used for the player's
+    /// game files, or the usual seed conflict error is shown once connected.
+    pub fn set_up_connection(
+        &mut self,
+        url: impl AsRef<str>,
+        slot: impl AsRef<str>,
+        seed: impl AsRef<str>,
+        password: Option<impl AsRef<str>>,
+    ) -> Result<()> {
+        self.config.set_url(url);
+        self.config.set_slot(slot);
+        self.config.set_seed(seed);
+        self.config.set_password(password);
+        self.config.save()?;
+        self.needs_setup = false;
+        self.log("Saved apconfig.json. Connecting...");
+        self.connect();
+        Ok(())
+    }
+
+    /// Returns whether the client is waiting for the player to load a save
+    /// before it connects.
+    pub fn is_waiting_for_save(&self) -> bool {
+        self.waiting_for_save
+    }
+
+    /// Abandons the current connection attempt, leaving the client
+    /// disconnected.
+    pub fn cancel_connection(&mut self) {
+        self.stop_auto_reconnect();
+        self.connection = Connection::none();
+        self.event_buffer.clear();
+        self.log("Connection cancelled.");
+    }
+
+    /// Returns how long the client has been trying to connect, or None if it's
+    /// not currently connecting.
+    pub fn connecting_duration(&self) -> Option<Duration> {
+        (self.connection_state_type() == ap::ConnectionStateType::Connecting)
+            .then(|| self.connection_start_time.elapsed())
     }
 
     /// Updates the URL to use to connect to Archipelago and reconnects the
     /// Archipelago session.
+    ///
+    /// If [url] is the same as the current URL and the connection is still
+    /// alive, this does nothing rather than needlessly dropping the connection.
     pub fn update_url(&mut self, url: impl AsRef<str>) -> Result<()> {
+        let password = self.config.password().map(str::to_string);
+        self.update_connection(url, password)
+    }
+
+    /// Updates the URL and password to use to connect to Archipelago and
+    /// reconnects the Archipelago session.
+    ///
+    /// If neither has changed and the connection is still alive, this does
+    /// nothing rather than needlessly dropping the connection.
+    ///
+    /// The slot name can't be changed this way. DS3Randomizer.exe bakes a
+    /// single slot's locations into the game files, so connecting as another
+    /// slot in the same multiworld would send this world's checks for the
+    /// other slot.
+    pub fn update_connection(
+        &mut self,
+        url: impl AsRef<str>,
+        password: Option<impl AsRef<str>>,
+    ) -> Result<()> {
+        let password = password.as_ref().map(|password| password.as_ref());
+        if is_current_connection(&self.config, url.as_ref(), password, self.is_disconnected()) {
+            return Ok(());
+        }
+
         if self.connection_state_type() == ap::ConnectionStateType::Disconnected {
             self.log("Reconnecting...");
         }
 
         self.config.set_url(url);
+        self.config.set_password(password);
         self.config.save()?;
-        self.connection = Self::new_connection(&self.config);
+        self.stop_auto_reconnect();
+        self.connect();
         Ok(())
     }
 
@@ -159,20 +1213,288 @@ impl Core {
         self.connection.client()
     }
 
-    /// Returns a mutable reference to the Archipelago client, if it's connected.
-    pub fn client_mut(&mut self) -> Option<&mut ap::Client<SlotData>> {
-        self.connection.client_mut()
+    /// Sends a chat message to the server.
+    ///
+    /// If the client isn't connected (for example because it disconnected
+    /// after the say input was rendered), this logs the message as unsent
+    /// instead so the player knows to retry it.
+    pub fn say(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        let Some(client) = self.connection.client_mut() else {
+            self.log_unsent(message, "disconnected");
+            return;
+        };
+
+        if let Err(err) = client.say(message.clone()) {
+            warn!("Failed to send message: {err}");
+            self.log_unsent(message, &err.to_string());
+        }
     }
 
-    /// Returns the list of all logs that have been emitted in the current
-    /// session.
-    pub fn logs(&self) -> impl ExactSizeIterator<Item = &ap::Print> {
-        self.log_buffer.iter()
+    /// Logs [message] in gray, indicating that it was not sent to the server
+    /// because of [reason].
+    fn log_unsent(&mut self, message: String, reason: &str) {
+        self.log(vec![
+            ap::RichText::Color {
+                text: message,
+                color: ap::TextColor::Black,
+            },
+            ap::RichText::Color {
+                text: format!(" (not sent: {reason})"),
+                color: ap::TextColor::Red,
+            },
+        ]);
     }
 
-    /// Runs the core logic of the mod. This may set [error], which should be
-    /// surfaced to the user.
-    pub fn update(&mut self) {
+    /// Returns the number of items received from the server that haven't been
+    /// granted to the player yet, or None if the client isn't connected or the
+    /// player isn't loaded into a game.
+    pub fn pending_item_count(&self) -> Option<usize> {
+        let client = self.connection.client()?;
+        let save = SaveData::instance()?;
+        Some(
+            client
+                .received_items()
+                .len()
+                .saturating_sub(save.items_granted)
+                + save.deferred_traps.len(),
+        )
+    }
+
+    /// Returns the items that have been granted to the player since the last
+    /// time this was called.
+    pub fn drain_granted_items(&mut self) -> impl Iterator<Item = GrantedItem> + '_ {
+        self.granted_items.drain(..)
+    }
+
+    /// Returns the number of locations the player has checked in the current
+    /// save, or None if they aren't loaded into a game.
+    pub fn checked_location_count(&self) -> Option<usize> {
+        SaveData::instance().map(|save| save.locations.len())
+    }
+
+    /// Returns the number of this player's locations the server knows have
+    /// been checked, along with the total number of locations in their world,
+    /// or None if the client isn't connected.
+    ///
+    /// Unlike [checked_location_count], this comes from the server, so it
+    /// reflects checks from any save and excludes locations that the player's
+    /// settings leave out of the multiworld.
+    pub fn location_progress(&self) -> Option<(usize, usize)> {
+        let client = self.connection.client()?;
+        let checked = client.checked_locations().len();
+        Some((checked, checked + client.missing_locations().len()))
+    }
+
+    /// Writes the locations the player has checked in the current save to a CSV
+    /// file in the mod directory, along with their names if they're known.
+    ///
+    /// Returns the path to the file that was written.
+    pub fn export_checked_locations(&self) -> Result<PathBuf> {
+        if !self.is_in_game() {
+            bail!("Not loaded into a game");
+        }
+        let Some(save) = SaveData::instance() else {
+            bail!("Not loaded into a game");
+        };
+
+        let mut locations = save.locations.iter().copied().collect::<Vec<_>>();
+        locations.sort();
+
+        let mut csv = String::from("id,name\n");
+        for id in locations {
+            let name = self
+                .connection
+                .client()
+                .and_then(|client| client.location_name(id))
+                .unwrap_or_default();
+            csv.push_str(&format!(
+                "{},\"{}\"\n",
+                id,
+                utils::location_name(name, Some(id)).replace('"', "\"\"")
+            ));
+        }
+
+        let path = utils::mod_directory()?.join(
+            Local::now()
+                .format("checked-locations-%Y-%m-%d-%H%M%S.csv")
+                .to_string(),
+        );
+        fs::write(&path, csv)?;
+        Ok(path)
+    }
+
+    /// Returns the number of locations the player has checked in the current
+    /// save that the server hasn't yet confirmed receiving, or None if the
+    /// client isn't connected or the player isn't loaded into a game.
+    ///
+    /// Locations the server doesn't list as missing for this slot are never
+    /// acknowledged, so they aren't counted.
+    pub fn unacknowledged_location_count(&self) -> Option<usize> {
+        let client = self.connection.client()?;
+        let save = SaveData::instance()?;
+        let missing = client.missing_locations();
+        Some(
+            save.locations
+                .difference(&save.acknowledged_locations)
+                .filter(|location| missing.contains(*location))
+                .count(),
+        )
+    }
+
+    /// Returns how long the server most recently took to acknowledge location
+    /// checks, or None if it hasn't acknowledged any over the current
+    /// connection.
+    ///
+    /// This isn't a true ping: it includes however long the server takes to
+    /// process the checks, and it's measured in game frames, so it's only
+    /// accurate to within a frame or so. It's still enough to tell a slow
+    /// connection from a healthy one.
+    pub fn check_confirmation_time(&self) -> Option<Duration> {
+        self.connection.client()?;
+        self.check_confirmation_time
+    }
+
+    /// Returns the number of players connected to the server and the total
+    /// number of players in the multiworld, or None if the client isn't
+    /// connected.
+    ///
+    /// The server doesn't tell clients who's already online when they connect,
+    /// only who joins or leaves afterwards. The number of online players is
+    /// None until the client has seen a join or part message for every other
+    /// player, since until then it can't know how many are online.
+    pub fn online_players(&self) -> Option<(Option<usize>, usize)> {
+        let client = self.connection.client()?;
+        let this_player = client.this_player().name();
+        let total = client.players().len();
+        let others = self
+            .online_players
+            .iter()
+            .filter(|(name, _)| name.as_str() != this_player);
+        if others.clone().count() + 1 < total {
+            return Some((None, total));
+        }
+
+        // This player is always online, since this client is connected.
+        let online = others.filter(|(_, count)| **count > 0).count() + 1;
+        Some((Some(online), total))
+    }
+
+    /// Returns the names and counts of progression items that the server has
+    /// yet to send this player, sorted by name.
+    ///
+    /// Returns `None` if the client isn't connected or if the slot data doesn't
+    /// list the player's progression items.
+    pub fn missing_progression_items(&self) -> Option<Vec<(String, u32)>> {
+        let client = self.connection.client()?;
+        let mut missing = client
+            .slot_data()
+            .progression_items
+            .as_ref()?
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect::<HashMap<_, _>>();
+        for item in client.received_items() {
+            if let Some(count) = missing.get_mut(item.item().name()) {
+                *count = count.saturating_sub(1);
+            }
+        }
+
+        let mut missing = missing
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(name, count)| (name.to_string(), count))
+            .collect::<Vec<_>>();
+        missing.sort();
+        Some(missing)
+    }
+
+    /// Returns whether the name of the item that [sender] sent this player from
+    /// [location] should be hidden in the overlay.
+    ///
+    /// This is only ever true if the slot data asks for received items to be
+    /// obscured, in which case items are hidden until they've actually been
+    /// granted in-game.
+    pub fn is_item_obscured(&self, sender: &str, location: i64) -> bool {
+        let Some(client) = self.connection.client() else {
+            return false;
+        };
+        if !client.slot_data().options.obscure_received_items || sender == self.config.slot() {
+            return false;
+        }
+
+        let Some(save) = SaveData::instance() else {
+            return true;
+        };
+        !client.received_items().iter().any(|item| {
+            item.index() < save.items_granted
+                && !save.deferred_traps.contains(&item.index())
+                && item.location().id() == location
+                && item.sender().name() == sender
+        })
+    }
+
+    /// Returns the hints involving this player that have been received this
+    /// session, with at most one per location.
+    pub fn hints(&self) -> &[ap::Print] {
+        &self.hints
+    }
+
+    /// Asks the server what the locations with the IDs in [locations] contain.
+    /// Locations that have already been scouted over this connection are
+    /// skipped.
+    ///
+    /// The server answers with a hint for each location, which is shown to
+    /// the player like any other hint and recorded for [scouted_location].
+    pub fn scout_locations(&mut self, locations: impl IntoIterator<Item = i64>) -> Result<()> {
+        let locations = locations
+            .into_iter()
+            .filter(|location| self.scouted_location(*location).is_none())
+            .collect::<Vec<_>>();
+        if locations.is_empty() {
+            return Ok(());
+        }
+        let Some(client) = self.connection.client_mut() else {
+            bail!("Not connected to Archipelago");
+        };
+
+        info!("Scouting location IDs: {:?}", locations);
+        client.create_hints(locations)?;
+        Ok(())
+    }
+
+    /// Returns the hint describing what the location with ID [location]
+    /// contains, or None if it hasn't been scouted over this connection.
+    pub fn scouted_location(&self, location: i64) -> Option<&ap::Print> {
+        self.scouted_locations.get(&location)
+    }
+
+    /// Returns the most recent logs that have been emitted in the current
+    /// session, up to [Config::log_buffer_limit].
+    pub fn logs(&self) -> impl ExactSizeIterator<Item = &ap::Print> {
+        self.log_buffer.iter()
+    }
+
+    /// Removes all messages from the overlay log. This doesn't affect the log
+    /// file or the persisted log history.
+    pub fn clear_log(&mut self) {
+        self.log_buffer.clear();
+    }
+
+    /// Returns the total number of logs that have been emitted in the current
+    /// session, including those that have since been dropped from [logs].
+    pub fn log_count(&self) -> usize {
+        self.log_count
+    }
+
+    /// Runs the core logic of the mod. This may set [error], which should be
+    /// surfaced to the user.
+    pub fn update(&mut self) {
+        if self.waiting_for_save && SaveData::instance().is_some() {
+            self.log("Save loaded, connecting...");
+            self.connect();
+        }
+
         self.update_always();
         if let Err(err) = self.update_live() {
             self.error = Some(err);
@@ -195,6 +1517,47 @@ impl Core {
     /// This is always run regardless of whether the client is connected or the
     /// mod has experienced a fatal error.
     fn update_always(&mut self) {
+        if self
+            .next_reconnect_time
+            .is_some_and(|time| time <= Instant::now())
+        {
+            self.next_reconnect_time = None;
+            self.reconnect_attempts += 1;
+            self.log(format!("Reconnect attempt {}...", self.reconnect_attempts));
+            self.retry_connection();
+        }
+
+        // The timeout covers the whole handshake, not just opening the socket,
+        // since a server that accepts the socket but never responds would
+        // otherwise leave the player waiting indefinitely.
+        if let Some(duration) = self.connecting_duration()
+            && duration >= self.config.connect_timeout()
+        {
+            info!(
+                "Connecting to {} timed out after {}s",
+                self.connection_url,
+                duration.as_secs()
+            );
+            self.connection = Connection::none();
+            self.event_buffer.clear();
+            self.log(vec![
+                ap::RichText::Color {
+                    text: "Connection timed out. ".into(),
+                    color: ap::TextColor::Red,
+                },
+                "Make sure the server session is running and that the URL, including its \
+                 port, is correct."
+                    .into(),
+            ]);
+            if self.reconnect_attempts > 0 {
+                self.schedule_reconnect();
+            }
+        }
+
+        // Pick up any transitions caused by the player since the last update,
+        // such as reconnecting or cancelling a connection.
+        self.sync_connection_state();
+
         use ap::Event::*;
         let mut state = self.connection.state_type();
         let mut events = self.connection.update();
@@ -204,50 +1567,71 @@ impl Core {
         for event in events.extract_if(.., |e| matches!(e, Connected | Error(_) | Print(_))) {
             match event {
                 Connected => {
+                    info!("Connected to {}", self.connection_url);
+                    if self.used_scheme_fallback {
+                        self.log(format!(
+                            "Connected using {}. Update your URL to connect directly next time.",
+                            self.connection_url
+                        ));
+                    }
                     state = ap::ConnectionStateType::Connected;
+                    self.stop_auto_reconnect();
+                    self.location_sender.reset();
+                    self.check_confirmation_time = None;
+                    self.scouted_locations.clear();
+                    self.session_stats = SessionStats {
+                        start_time: Some(Instant::now()),
+                        ..Default::default()
+                    };
+                    self.online_players.clear();
+                    self.switch_log_file();
                 }
+                // A scheme fallback replaces the connection before its state
+                // is synced, so the failed attempt is never reported as a
+                // disconnection. Other fatal errors are reported by
+                // [on_connection_state_change].
                 Error(err) if err.is_fatal() => {
-                    let err = self.connection.err();
-                    self.log(
-                        if matches!(err, ap::Error::WebSocket(tungstenite::Error::Io(io))
-                                         if io.kind() == io::ErrorKind::ConnectionRefused)
-                        {
-                            vec![
-                                ap::RichText::Color {
-                                    text: "Connection refused. ".into(),
-                                    color: ap::TextColor::Red,
-                                },
-                                "Make sure the server session is running and the URL is \
-                                 up-to-date."
-                                    .into(),
-                            ]
-                        } else if state == ap::ConnectionStateType::Connected {
-                            vec![
-                                ap::RichText::Color {
-                                    text: "Connection failed: ".into(),
-                                    color: ap::TextColor::Red,
-                                },
-                                err.to_string().into(),
-                            ]
-                        } else {
-                            vec![
-                                ap::RichText::Color {
-                                    text: "Disconnected: ".into(),
-                                    color: ap::TextColor::Red,
-                                },
-                                err.to_string().into(),
-                            ]
-                        },
-                    );
-                    self.event_buffer.clear();
+                    if state != ap::ConnectionStateType::Connected {
+                        self.try_scheme_fallback();
+                    }
                 }
                 Error(err) => self.log(err.to_string()),
                 Print(print) => {
                     info!("[APS] {print}");
-                    if self.log_buffer.len() >= LOG_BUFFER_LIMIT {
-                        self.log_buffer.pop_front();
+                    match &print {
+                        ap::Print::Join { player, .. } => {
+                            *self
+                                .online_players
+                                .entry(player.name().to_string())
+                                .or_default() += 1;
+                        }
+                        ap::Print::Part { player, .. } => {
+                            if let Some(count) = self.online_players.get_mut(player.name()) {
+                                *count = count.saturating_sub(1);
+                            }
+                        }
+                        ap::Print::Hint { item, .. }
+                            if self.config.slot() == item.receiver().name()
+                                || self.config.slot() == item.sender().name() =>
+                        {
+                            // The server resends hints when their status
+                            // changes, so replace the old version.
+                            self.hints.retain(|hint| match hint {
+                                ap::Print::Hint { item: old, .. } => {
+                                    old.location().id() != item.location().id()
+                                        || old.sender().name() != item.sender().name()
+                                }
+                                _ => true,
+                            });
+                            self.hints.push(print.clone());
+                            if self.config.slot() == item.sender().name() {
+                                self.scouted_locations
+                                    .insert(item.location().id(), print.clone());
+                            }
+                        }
+                        _ => {}
                     }
-                    self.log_buffer.push_back(print);
+                    self.push_log(print);
                 }
                 _ => {}
             }
@@ -258,6 +1642,162 @@ impl Core {
         } else {
             debug_assert!(self.event_buffer.is_empty());
         }
+
+        self.sync_connection_state();
+    }
+
+    /// Checks whether [connection] has changed state since the last call and,
+    /// if so, runs [on_connection_state_change].
+    fn sync_connection_state(&mut self) {
+        let state = self.connection.state_type();
+        if state != self.last_state {
+            let old = mem::replace(&mut self.last_state, state);
+            self.on_connection_state_change(old, state);
+        }
+    }
+
+    /// Registers [listener] to run whenever [connection] moves from one state
+    /// to another, whether because of the server, the network, or the player.
+    /// It's passed the old and new states.
+    ///
+    /// This is the place to put logic that should run whenever the connection
+    /// is established or lost, regardless of why. Listeners run in the order
+    /// they were registered and are never unregistered.
+    pub fn add_connection_state_listener(
+        &mut self,
+        listener: impl FnMut(&mut Core, ap::ConnectionStateType, ap::ConnectionStateType)
+        + Send
+        + 'static,
+    ) {
+        self.connection_state_listeners.push(Box::new(listener));
+    }
+
+    /// Runs the [connection_state_listeners] for a transition of [connection]
+    /// from [old] to [new].
+    fn on_connection_state_change(
+        &mut self,
+        old: ap::ConnectionStateType,
+        new: ap::ConnectionStateType,
+    ) {
+        info!("Connection state changed from {old:?} to {new:?}");
+
+        // Take the listeners out so that they can have mutable access to the
+        // rest of the core. Any registered while they run are kept.
+        let mut listeners = mem::take(&mut self.connection_state_listeners);
+        for listener in &mut listeners {
+            listener(self, old, new);
+        }
+        listeners.append(&mut self.connection_state_listeners);
+        self.connection_state_listeners = listeners;
+    }
+
+    /// Reports why [connection] disconnected from [old] and decides whether to
+    /// automatically reconnect.
+    ///
+    /// This does nothing if the connection didn't end because of an error, such
+    /// as when the player cancels it, since whatever ended it already told the
+    /// player.
+    fn on_disconnect(&mut self, old: ap::ConnectionStateType) {
+        let Some(err) = self.connection.error() else {
+            return;
+        };
+        info!("Connection error: {err:?}");
+        let closed_by_server = classify_disconnect(old, err) == DisconnectKind::ClosedByServer;
+        // Only retry connections that were working, or that we're already
+        // retrying, so that a bad URL doesn't spin forever. Servers that close
+        // the connection are usually restarting, so those are retried as well.
+        let retry = is_retryable_connection_error(err)
+            && (old == ap::ConnectionStateType::Connected || self.reconnect_attempts > 0);
+        let message = if closed_by_server {
+            vec![
+                ap::RichText::Color {
+                    text: "The server closed the connection. ".into(),
+                    color: ap::TextColor::Red,
+                },
+                "It may have restarted or shut down the room.".into(),
+            ]
+        } else if let Some((summary, advice)) = describe_connection_error(err) {
+            vec![
+                ap::RichText::Color {
+                    text: format!("{summary} "),
+                    color: ap::TextColor::Red,
+                },
+                advice.into(),
+            ]
+        } else if old == ap::ConnectionStateType::Connected {
+            vec![
+                ap::RichText::Color {
+                    text: "Connection failed: ".into(),
+                    color: ap::TextColor::Red,
+                },
+                err.to_string().into(),
+            ]
+        } else {
+            vec![
+                ap::RichText::Color {
+                    text: "Disconnected: ".into(),
+                    color: ap::TextColor::Red,
+                },
+                err.to_string().into(),
+            ]
+        };
+
+        self.log(message);
+        self.event_buffer.clear();
+        if retry {
+            self.schedule_reconnect();
+        } else {
+            self.stop_auto_reconnect();
+        }
+    }
+
+    /// Logs a summary of [session_stats] if there's an active session, and
+    /// ends that session.
+    fn log_session_summary(&mut self) {
+        let stats = mem::take(&mut self.session_stats);
+        let Some(start_time) = stats.start_time else {
+            return;
+        };
+
+        let duration = start_time.elapsed().as_secs();
+        self.log(ap::RichText::Color {
+            text: format!(
+                "Session summary: {}h{:02}m{:02}s connected, {} items received, {} locations \
+                 checked, {} death links sent, {} death links received.",
+                duration / 3600,
+                duration / 60 % 60,
+                duration % 60,
+                stats.items_received,
+                stats.locations_checked,
+                stats.death_links_sent,
+                stats.death_links_received,
+            ),
+            color: ap::TextColor::Green,
+        });
+    }
+
+    /// Wraps up the current session when the game is exiting, logging its
+    /// summary as though the connection had been closed.
+    pub fn shut_down(&mut self) {
+        info!("Shutting down");
+        self.log_session_summary();
+    }
+
+    /// If the config asks for per-seed logs, redirects the log file to the
+    /// directory for the connected seed.
+    fn switch_log_file(&self) {
+        if !self.config.log_per_seed() {
+            return;
+        }
+        let Some(client) = self.connection.client() else {
+            return;
+        };
+
+        match log_file::switch_to_seed(client.seed_name()) {
+            Ok(Some(dir)) => info!("Now logging to {}", dir.to_string_lossy()),
+            Ok(None) => {}
+            Err(err) => warn!("Failed to switch to per-seed log file: {err}"),
+        }
     }
 
     /// Updates the game logic and checks for common errors. This does nothing
@@ -276,7 +1816,7 @@ impl Core {
         }
 
         if let Some(time) = self.load_time
-            && time.elapsed() < GRACE_PERIOD
+            && time.elapsed() < self.grace_period()
         {
             return Ok(());
         }
@@ -284,6 +1824,11 @@ impl Core {
         self.check_version_conflict()?;
 
         self.check_seed_conflict()?;
+        if self.awaiting_save_association() {
+            // Don't touch the save until the player confirms that it belongs
+            // to this seed.
+            return Ok(());
+        }
         if let Some(save_data) = SaveData::instance_mut().as_mut()
             && save_data.seed.is_none()
         {
@@ -296,10 +1841,21 @@ impl Core {
         // loaded and is actively playing.
         use ap::Event::*;
         for event in mem::take(&mut self.event_buffer) {
-            if let DeathLink { source, time, .. } = event {
-                self.receive_death_link(source, time)
+            if let DeathLink {
+                source,
+                time,
+                cause,
+                ..
+            } = event
+            {
+                self.receive_death_link(source, time, cause)
             }
         }
+        self.report_ignored_death_links();
+
+        if !self.is_in_game() {
+            return Ok(());
+        }
 
         self.send_death_link()?;
         self.process_incoming_items(&item_man);
@@ -310,12 +1866,73 @@ impl Core {
         Ok(())
     }
 
+    /// Returns whether the player is fully loaded into a game, and so whether
+    /// it's safe to inspect and modify in-game state such as the inventory.
+    ///
+    /// This is true only when all of the following hold:
+    ///
+    /// * [MapItemMan] exists, which means the player isn't on the main menu.
+    ///   This also means that [SaveData] is available.
+    ///
+    /// * The grace period since [MapItemMan] came into existence has elapsed.
+    ///   Other managers may exist before this point while the player's data is
+    ///   still in an indeterminate state.
+    ///
+    /// * [GameDataMan] and [PlayerIns] exist, which means the player's
+    ///   inventory and character are available.
+    pub fn is_in_game(&self) -> bool {
+        self.load_time
+            .is_some_and(|time| time.elapsed() >= self.grace_period())
+            && unsafe { MapItemMan::instance() }.is_ok()
+            && unsafe { GameDataMan::instance() }.is_ok()
+            && unsafe { PlayerIns::instance() }.is_ok()
+    }
+
+    /// Returns the grace period to wait after loading into a game before the
+    /// mod starts taking actions.
+    fn grace_period(&self) -> Duration {
+        if self.granted_this_session {
+            RELOAD_GRACE_PERIOD
+        } else {
+            GRACE_PERIOD
+        }
+    }
+
+    /// Returns how much longer the mod will wait after the player loaded into a
+    /// game before it starts granting items, or None if it's not currently
+    /// waiting (or isn't connected).
+    pub fn remaining_grace_period(&self) -> Option<Duration> {
+        self.connection.client()?;
+        self.grace_period()
+            .checked_sub(self.load_time?.elapsed())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Returns the version of the static randomizer that generated the config,
+    /// if it doesn't match this client's version.
+    pub fn mismatched_randomizer_version(&self) -> Option<&str> {
+        self.config
+            .client_version()
+            .filter(|version| *version != env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Returns the checksums of the Dark Souls III data package that the static
+    /// randomizer used and that the connected server is using, in that order,
+    /// if they don't match.
+    ///
+    /// A mismatch means the server's apworld is a different version than the
+    /// one the game was randomized for, so Archipelago item IDs may map to the
+    /// wrong DS3 items.
+    pub fn mismatched_data_package(&self) -> Option<(&str, &str)> {
+        let expected = self.config.data_package_checksum()?;
+        let actual = self.connection.client()?.this_game().checksum();
+        (expected != actual).then_some((expected, actual))
+    }
+
     /// Returns an error if the user's static randomizer version doesn't match
     /// this mod's version.
     fn check_version_conflict(&self) -> Result<()> {
-        if let Some(client_version) = self.config().client_version()
-            && client_version != env!("CARGO_PKG_VERSION")
-        {
+        if let Some(client_version) = self.mismatched_randomizer_version() {
             bail!(
                 "Your apconfig.json was generated using static randomizer v{}, but this client is \
                  v{}. Re-run the static randomizer with the current version.",
@@ -330,41 +1947,26 @@ impl Core {
     /// Returns an error if there's a conflict between the notion of the current
     /// seed in the server, the save, and/or the config. Also updates the save
     /// data's notion based on whatever is available if it doesn't exist yet.
-    fn check_seed_conflict(&mut self) -> Result<()> {
+    fn check_seed_conflict(&self) -> Result<(), SeedConflict> {
         let client_seed = self.connection.client().map(|c| c.seed_name());
         let save = SaveData::instance();
-        let save_seed = save.as_ref().and_then(|s| s.seed.as_ref());
+        let save_seed = save.as_ref().and_then(|s| s.seed.as_deref());
+        seed_conflict(client_seed, save_seed, self.config.seed())
+    }
 
-        match (client_seed, save_seed) {
-            (Some(client_seed), _) if client_seed != self.config.seed() => bail!(
-                "You've connected to a different Archipelago multiworld than the one that \
-                 DS3Randomizer.exe used!\n\
-                 \n\
-		 Connected room seed: {}\n\
-                 DS3Randomizer.exe seed: {}",
-                client_seed,
-                self.config.seed()
-            ),
-            (Some(client_seed), Some(save_seed)) if client_seed != save_seed => bail!(
-                "You've connected to a different Archipelago multiworld than the one that \
-                 you used before with this save!\n\
-                 \n\
-		 Connected room seed: {}\n\
-		 Save file seed: {}",
-                client_seed,
-                save_seed
-            ),
-            (_, Some(save_seed)) if self.config.seed() != save_seed => bail!(
-                "Your most recent DS3Randomizer.exe invocation connected to a different \
-                 Archipealgo multiworld than the one that you used before with this save!\n\
-                 \n\
-                 DS3Randomizer.exe seed: {}\n\
-                 Save file seed: {}",
-                self.config.seed(),
-                save_seed
-            ),
-            _ => Ok(()),
-        }
+    /// Immediately re-runs [check_seed_conflict] rather than waiting for the
+    /// next update. If there's no longer a conflict (for example because the
+    /// player loaded the correct save), clears the fatal error so the mod can
+    /// resume.
+    ///
+    /// This should only be called when the fatal error the user is looking at
+    /// is a [SeedConflict]. A genuine conflict is still returned as an error,
+    /// and [update] will continue to check for conflicts as normal afterwards.
+    pub fn recheck_seed_conflict(&mut self) -> Result<(), SeedConflict> {
+        self.check_seed_conflict()?;
+        info!("Seed conflict resolved");
+        self.error = None;
+        Ok(())
     }
 
     /// Returns an error if [config] expects DLC to be installed and it is not.
@@ -412,124 +2014,412 @@ impl Core {
             return;
         };
 
-        // Wait a second between each item grant, and 10 seconds after we load
-        // in before we start granting items at all.
-        if self.last_item_time.elapsed().as_secs() < 1
-            || self.load_time.is_none_or(|i| i.elapsed().as_secs() < 10)
-        {
+        let pending = pending_items(client, save_data);
+        if pending > CATCH_UP_THRESHOLD {
+            self.catching_up = true;
+        } else if pending == 0 {
+            self.catching_up = false;
+        }
+
+        if !seed_matches(client, save_data) {
             return;
         }
 
-        if let Some(item) = client
-            .received_items()
-            .iter()
-            .find(|item| item.index() >= save_data.items_granted)
-        {
-            let id_key = I64Key(item.item().id());
-            let ds3_id = client
-                .slot_data()
-                .ap_ids_to_item_ids
-                .get(&id_key)
-                .unwrap_or_else(|| {
-                    panic!(
-                        "Archipelago item {:?} should have a DS3 ID defined in slot data",
-                        item.item()
-                    )
-                })
-                .0;
-            let quantity = client
-                .slot_data()
-                .item_counts
-                .get(&id_key)
-                .copied()
-                .unwrap_or(1);
+        let times = GrantTimes {
+            last_item: self.last_item_time,
+            last_trap: self.last_trap_time,
+            item_interval: self.item_grant_interval(),
+            trap_interval: self.config.trap_interval(),
+        };
+        let find_item = move |index: usize| {
+            client
+                .received_items()
+                .iter()
+                .find(|item| item.index() == index)
+        };
+        let action = next_item_action(
+            save_data.items_granted,
+            &save_data.deferred_traps,
+            self.item_index_ceiling,
+            |index| find_item(index).map(|item| item.item().is_trap()),
+            times,
+            Instant::now(),
+        );
+        let (index, is_deferred) = match action {
+            ItemAction::Wait => return,
+            ItemAction::Grant(index) | ItemAction::Defer(index) => (index, false),
+            ItemAction::GrantDeferred(index) => (index, true),
+        };
+        let Some(item) = find_item(index) else {
+            return;
+        };
+
+        if action == ItemAction::Defer(index) {
+            info!(
+                "Deferring {} until the trap interval has passed",
+                item.item().name()
+            );
+            save_data.deferred_traps.push(index);
+            save_data.items_granted += 1;
+            return;
+        }
+
+        let is_trap = item.item().is_trap();
+        let dispatch = dispatch_item(client.slot_data(), item.item().id());
+        if let Some(effect) = dispatch.effect {
+            info!(
+                "Applying effect {:?} for {} (AP ID {} from {})",
+                effect,
+                item.item().name(),
+                item.item().id(),
+                utils::location_name(item.location().name(), Some(item.location().id()))
+            );
+            match Self::apply_item_effect(effect) {
+                // Don't report an effect's death to the rest of the multiworld
+                // as a death link.
+                Ok(()) if effect == ItemEffect::Kill => self.last_death_link = Instant::now(),
+                Ok(()) => {}
+                Err(err) => warn!("Failed to apply item effect {:?}: {}", effect, err),
+            }
+        }
 
+        if dispatch.grant {
+            let (ds3_id, quantity) = Self::ds3_item(client.slot_data(), item.item().id());
             info!(
                 "Granting {} (AP ID {}, DS3 ID {:?} from {})",
                 item.item().name(),
                 item.item().id(),
                 ds3_id,
-                item.location().name()
+                utils::location_name(item.location().name(), Some(item.location().id()))
             );
+            Self::grant_ds3_item(
+                client.slot_data(),
+                item_man,
+                player_game_data,
+                ds3_id,
+                quantity,
+            );
+        }
 
-            // Grant Path of the Dragon as a gesture rather than an item.
-            if ds3_id.category() == ItemCategory::Goods && ds3_id.param_id() == 9030 {
-                player_game_data.grant_gesture(29, ds3_id);
-            } else {
-                item_man.grant_item(ItemBufferEntry {
-                    id: ds3_id,
-                    quantity,
-                    durability: -1,
-                });
-            }
-
+        let class = ItemClass::of(item.item());
+        if self.config.item_sound_cues() {
+            sound::play_item_cue(class);
+        }
+        self.granted_items.push(GrantedItem {
+            name: item.item().name().to_string(),
+            class,
+            sender: item.sender().name().to_string(),
+        });
+        if is_deferred {
+            save_data.deferred_traps.remove(0);
+        } else {
             save_data.items_granted += 1;
-            self.last_item_time = Instant::now();
         }
+        self.session_stats.items_received += 1;
+        self.last_item_time = Instant::now();
+        if is_trap {
+            self.last_trap_time = Some(self.last_item_time);
+        }
+        self.granted_this_session = true;
     }
 
-    /// Removes any placeholder items from the player's inventory and notifies
-    /// the server that they've been accessed.
-    fn process_inventory_items(&mut self) -> Result<()> {
-        let Some(ref mut save_data) = SaveData::instance_mut() else {
-            return Ok(());
+    /// Grants the player another copy of an item they've already received from
+    /// the server, without changing which items are recorded as granted. This
+    /// is a recovery tool for items that were lost to bugs.
+    ///
+    /// Returns the name of the regranted item.
+    #[cfg(debug_assertions)]
+    pub fn regrant_item(&self, ap_id: i64) -> Result<String> {
+        let Some(client) = self.connection.client() else {
+            bail!("Not connected to Archipelago");
         };
-        let Ok(game_data_man) = (unsafe { GameDataMan::instance() }) else {
-            return Ok(());
+        if !self.is_in_game() {
+            bail!("Not loaded into a game");
+        }
+        let (Ok(item_man), Ok(player_game_data)) = (unsafe { MapItemMan::instance() }, unsafe {
+            PlayerGameData::instance()
+        }) else {
+            bail!("Not loaded into a game");
         };
-        let Ok(regulation_manager) = (unsafe { CSRegulationManager::instance() }) else {
-            return Ok(());
+        if SaveData::instance().is_none_or(|save| !seed_matches(client, &save)) {
+            bail!("This save isn't associated with the connected seed");
+        }
+        let Some(item) = client
+            .received_items()
+            .iter()
+            .find(|item| item.item().id() == ap_id)
+        else {
+            bail!("Archipelago item {} hasn't been received", ap_id);
         };
 
-        // We have to make a separate vector here so we aren't borrowing while
-        // we make mutations.
-        let ids = game_data_man
-            .main_player_game_data
-            .equipment
-            .equip_inventory_data
-            .items_data
-            .items()
-            .map(|e| e.item_id)
-            .collect::<Vec<_>>();
-        for id in ids {
-            if !id.is_archipelago() {
-                continue;
-            }
+        let (ds3_id, quantity) = Self::ds3_item(client.slot_data(), ap_id);
+        warn!(
+            "Regranting {} (AP ID {}, DS3 ID {:?}) by request",
+            item.item().name(),
+            ap_id,
+            ds3_id,
+        );
+        Self::grant_ds3_item(
+            client.slot_data(),
+            item_man,
+            player_game_data,
+            ds3_id,
+            quantity,
+        );
+        Ok(item.item().name().to_string())
+    }
 
-            info!("Inventory contains Archipelago item {:?}", id);
-            let row = regulation_manager
-                .get_equip_param(id)
-                .unwrap_or_else(|| panic!("no row defined for Archipelago ID {:?}", id));
-            let row = row.as_dyn();
+    /// Stops delivering items once the player has received all those with
+    /// indices less than [ceiling], or resumes delivering all items if
+    /// [ceiling] is None.
+    #[cfg(debug_assertions)]
+    pub fn set_item_index_ceiling(&mut self, ceiling: Option<usize>) {
+        self.item_index_ceiling = ceiling;
+        match ceiling {
+            Some(ceiling) => warn!("Only delivering items with indices below {}", ceiling),
+            None => info!("Delivering all items"),
+        }
+    }
 
-            info!("  Archipelago location: {}", row.archipelago_location_id());
-            save_data.locations.insert(row.archipelago_location_id());
+    /// Handles a fake death link as though it had been received from the
+    /// server, including respecting the death link grace period. This allows
+    /// death link to be tested without a second player.
+    #[cfg(debug_assertions)]
+    pub fn simulate_received_death_link(&mut self) -> Result<()> {
+        if self.connection.client().is_none() {
+            bail!("Not connected to Archipelago");
+        }
+        if !self.is_in_game() {
+            bail!("Not loaded into a game");
+        }
 
-            if let EquipParamStruct::EQUIP_PARAM_GOODS_ST(good) = row.as_enum()
-                && good.icon_id() == 7039
-            {
-                info!("  Item is Path of the Dragon, granting gesture");
-                // If the player gets the synthetic Path of the Dragon item,
-                // give them the gesture itself instead. Don't display an
-                // item pop-up, because they already saw one when they got
-                // the item.
-                game_data_man
-                    .main_player_game_data
-                    .gesture_data
-                    .set_gesture_acquired(29, true);
-            } else if let Some((real_id, quantity)) = row.archipelago_item() {
-                info!("  Converting to {}x {:?}", quantity, real_id);
-                game_data_man.give_item_directly(real_id, quantity);
-            } else {
-                // Presumably any item without local item data is a foreign
-                // item, but we'll log a bunch of extra data in case there's a
-                // bug we need to track down.
-                info!(
-                    "  Item has no local item data. Basic price: {}, sell value: {}{}",
-                    row.basic_price(),
-                    row.sell_value(),
-                    if let EquipParamStruct::EQUIP_PARAM_GOODS_ST(good) = row.as_enum() {
-                        format!(", icon id: {}", good.icon_id())
+        self.receive_death_link(
+            "[simulated]".to_string(),
+            SystemTime::now(),
+            Some("a simulated death link".to_string()),
+        );
+        Ok(())
+    }
+
+    /// Kills the player so that the normal death link logic sends a death
+    /// link (subject to amnesty and the grace period) on the next update.
+    #[cfg(debug_assertions)]
+    pub fn simulate_sent_death_link(&mut self) -> Result<()> {
+        if self.connection.client().is_none() {
+            bail!("Not connected to Archipelago");
+        }
+        if !self.is_in_game() {
+            bail!("Not loaded into a game");
+        }
+        let Ok(player) = (unsafe { PlayerIns::instance() }) else {
+            bail!("Not loaded into a game");
+        };
+
+        player.kill();
+        Ok(())
+    }
+
+    /// Returns whether the player has loaded a save that has no Archipelago
+    /// data, such as one created before the mod was installed, and that hasn't
+    /// yet been associated with a seed.
+    ///
+    /// Items won't be granted to such a save until the player confirms it
+    /// belongs to the connected seed with [associate_save].
+    pub fn awaiting_save_association(&self) -> bool {
+        self.connection.client().is_some()
+            && SaveData::loaded_without_data()
+            && SaveData::instance().is_some_and(|save_data| save_data.seed.is_none())
+    }
+
+    /// Associates the currently-loaded save with the seed from the config, as
+    /// confirmation that a save without Archipelago data belongs to it.
+    pub fn associate_save(&mut self) -> Result<()> {
+        let Some(mut save_data) = SaveData::instance_mut() else {
+            bail!("Not loaded into a game");
+        };
+        info!("Associating save with seed {}", self.config.seed());
+        save_data.seed = Some(self.config.seed().to_string());
+        Ok(())
+    }
+
+    /// Returns the minimum time between granting two items.
+    ///
+    /// This is measured in wall-clock time, so it (along with [GRACE_PERIOD])
+    /// keeps running while the game is alt-tabbed. That's intentional: DS3
+    /// itself keeps running in the background, and because at most one item is
+    /// granted per update, a long pause never allows more than a single item
+    /// through at once. The backlog is still doled out one item per interval
+    /// when the player returns.
+    fn item_grant_interval(&self) -> Duration {
+        if self.catching_up {
+            CATCH_UP_GRANT_INTERVAL.min(self.config.item_grant_interval())
+        } else {
+            self.config.item_grant_interval()
+        }
+    }
+
+    /// Returns the DS3 item ID and quantity that correspond to the Archipelago
+    /// item with ID [ap_id].
+    fn ds3_item(slot_data: &SlotData, ap_id: i64) -> (ItemId, u32) {
+        let id_key = I64Key(ap_id);
+        let ds3_id = slot_data
+            .ap_ids_to_item_ids
+            .get(&id_key)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Archipelago item {} should have a DS3 ID defined in slot data",
+                    ap_id
+                )
+            })
+            .0;
+        let quantity = slot_data.item_counts.get(&id_key).copied().unwrap_or(1);
+        (ds3_id, quantity)
+    }
+
+    /// Applies [effect] to the player. Returns an error if it can't be applied
+    /// right now.
+    ///
+    /// Callers are responsible for making sure that [ItemEffect::Kill] isn't
+    /// sent as a death link.
+    fn apply_item_effect(effect: ItemEffect) -> Result<()> {
+        let Ok(player) = (unsafe { PlayerIns::instance() }) else {
+            bail!("Player isn't loaded");
+        };
+
+        match effect {
+            ItemEffect::FullHeal => {
+                let data = &mut player.super_chr_ins.modules.data;
+                data.hp = data.max_hp;
+            }
+            ItemEffect::Kill => player.kill(),
+            ItemEffect::WarpToBonfire => {
+                let Ok(game_man) = (unsafe { GameMan::instance() }) else {
+                    bail!("GameMan isn't available");
+                };
+                game_man.warp_to_last_bonfire();
+            }
+        }
+        Ok(())
+    }
+
+    /// If [id] is an item that represents a gesture, returns the ID of that
+    /// gesture. This includes both gestures the client knows about natively
+    /// and any additional gestures defined in [slot_data].
+    fn gesture_for(slot_data: &SlotData, id: ItemId) -> Option<u32> {
+        id.gesture_id().or_else(|| {
+            if id.category() != ItemCategory::Goods {
+                return None;
+            }
+            slot_data
+                .gesture_items
+                .get(&I64Key(id.param_id().into()))
+                .copied()
+        })
+    }
+
+    /// Gives [quantity] copies of [ds3_id] to the player.
+    fn grant_ds3_item(
+        slot_data: &SlotData,
+        item_man: &MapItemMan,
+        player_game_data: &mut PlayerGameData,
+        ds3_id: ItemId,
+        quantity: u32,
+    ) {
+        // Grant gestures as gestures rather than items.
+        if let Some(gesture) = Self::gesture_for(slot_data, ds3_id) {
+            player_game_data.grant_gesture(gesture, ds3_id);
+        } else {
+            item_man.grant_item(ItemBufferEntry {
+                id: ds3_id,
+                quantity,
+                durability: -1,
+            });
+        }
+    }
+
+    /// Removes any placeholder items from the player's inventory and notifies
+    /// the server that they've been accessed.
+    fn process_inventory_items(&mut self) -> Result<()> {
+        let Some(ref mut save_data) = SaveData::instance_mut() else {
+            return Ok(());
+        };
+        let Ok(game_data_man) = (unsafe { GameDataMan::instance() }) else {
+            return Ok(());
+        };
+        let Ok(regulation_manager) = (unsafe { CSRegulationManager::instance() }) else {
+            return Ok(());
+        };
+
+        // We have to make a separate vector here so we aren't borrowing while
+        // we make mutations.
+        let ids = game_data_man
+            .main_player_game_data
+            .equipment
+            .equip_inventory_data
+            .items_data
+            .items()
+            .map(|e| e.item_id)
+            .collect::<Vec<_>>();
+        let mut new_locations = Vec::new();
+        for id in ids {
+            if !id.is_archipelago() {
+                continue;
+            }
+
+            info!("Inventory contains Archipelago item {:?}", id);
+            let Some(row) = regulation_manager.get_equip_param(id) else {
+                warn!("  No row defined for Archipelago ID {:?}, skipping", id);
+                continue;
+            };
+            let row = row.as_dyn();
+            if !row.is_archipelago() {
+                warn!("  Row has no Archipelago location, skipping");
+                continue;
+            }
+
+            info!("  Archipelago location: {}", row.archipelago_location_id());
+            if save_data.locations.insert(row.archipelago_location_id()) {
+                self.session_stats.locations_checked += 1;
+                new_locations.push(row.archipelago_location_id());
+            }
+
+            let real_item = row.archipelago_item();
+            let gesture = real_item
+                .and_then(|(real_id, _)| {
+                    Self::gesture_for(self.connection.client()?.slot_data(), real_id)
+                })
+                .or_else(|| match row.as_enum() {
+                    // The synthetic Path of the Dragon item doesn't have local
+                    // item data, so we identify it by its icon instead.
+                    EquipParamStruct::EQUIP_PARAM_GOODS_ST(good) if good.icon_id() == 7039 => {
+                        Some(PATH_OF_THE_DRAGON_GESTURE)
+                    }
+                    _ => None,
+                });
+
+            if let Some(gesture) = gesture {
+                info!("  Item is a gesture, granting gesture {}", gesture);
+                // If the player gets a synthetic gesture item, give them the
+                // gesture itself instead. Don't display an item pop-up,
+                // because they already saw one when they got the item.
+                game_data_man
+                    .main_player_game_data
+                    .gesture_data
+                    .set_gesture_acquired(gesture, true);
+            } else if let Some((real_id, quantity)) = real_item {
+                info!("  Converting to {}x {:?}", quantity, real_id);
+                game_data_man.give_item_directly(real_id, quantity);
+            } else {
+                // Presumably any item without local item data is a foreign
+                // item, but we'll log a bunch of extra data in case there's a
+                // bug we need to track down.
+                info!(
+                    "  {:?} item has no local item data. Basic price: {}, sell value: {}{}",
+                    id.category(),
+                    row.basic_price(),
+                    row.sell_value(),
+                    if let EquipParamStruct::EQUIP_PARAM_GOODS_ST(good) = row.as_enum() {
+                        format!(", icon id: {}", good.icon_id())
                     } else {
                         "".into()
                     }
@@ -539,20 +2429,69 @@ impl Core {
             game_data_man.remove_item(id, 1);
         }
 
-        if let Some(client) = self.connection.client_mut()
-            && save_data.locations.len() > self.locations_sent
+        if self.config.log_checked_locations() && !new_locations.is_empty() {
+            self.log_checked_locations(&new_locations);
+        }
+
+        let Some(client) = self.connection.client_mut() else {
+            return Ok(());
+        };
+
+        if let Some(time) = self
+            .location_sender
+            .sync(client, save_data, Instant::now())?
         {
-            client.mark_checked(save_data.locations.iter().copied())?;
-            self.locations_sent = save_data.locations.len();
+            self.check_confirmation_time = Some(time);
         }
         Ok(())
     }
 
-    /// Kills the player after a death link is received.
-    fn receive_death_link(&mut self, source: String, time: SystemTime) {
-        if !self.allow_death_link() {
+    /// Logs a single line listing [locations], grouped by area.
+    ///
+    /// DS3 location names begin with an abbreviation of the area they're in
+    /// followed by a colon (for example `"FS: Estus Shard"`), so we use that
+    /// prefix as the group. Locations whose names don't follow that pattern
+    /// (or whose names aren't known at all) are grouped under "Other".
+    fn log_checked_locations(&mut self, locations: &[i64]) {
+        let Some(client) = self.connection.client() else {
             return;
+        };
+
+        // Use a Vec rather than a map to preserve the order in which areas
+        // were encountered.
+        let mut groups = Vec::<(String, Vec<String>)>::new();
+        for id in locations {
+            let name =
+                utils::location_name(client.location_name(*id).unwrap_or_default(), Some(*id));
+            let (area, name) = match name.split_once(": ") {
+                Some((area, name)) if !area.contains(' ') => (area.to_string(), name.to_string()),
+                _ => ("Other".to_string(), name),
+            };
+            match groups.iter_mut().find(|(group, _)| *group == area) {
+                Some((_, names)) => names.push(name),
+                None => groups.push((area, vec![name])),
+            }
         }
+
+        let summary = groups
+            .into_iter()
+            .map(|(area, names)| format!("{} — {}", area, names.join(", ")))
+            .collect::<Vec<_>>()
+            .join("; ");
+        self.log(ap::RichText::Color {
+            text: format!("Checked: {}", summary),
+            color: ap::TextColor::Cyan,
+        });
+    }
+
+    /// Kills the player after a death link is received.
+    ///
+    /// If the config has a [Config::death_link_message], this also sends it to
+    /// the server. This is only done for death links that actually kill the
+    /// player, so it's naturally limited to one message per
+    /// [Config::death_link_grace_period].
+    fn receive_death_link(&mut self, source: String, time: SystemTime, cause: Option<String>) {
+        // Always ignore death links that we sent.
         if self
             .connection
             .client()
@@ -560,30 +2499,90 @@ impl Core {
         {
             return;
         }
+        if self.death_link_paused {
+            info!(
+                "Ignoring death link from {} because death link is paused",
+                source
+            );
+            return;
+        }
+        if !self.config.receive_death_links() {
+            info!(
+                "Ignoring death link from {} because receiving death links is disabled",
+                source
+            );
+            return;
+        }
+        if !self.is_past_intro() {
+            info!(
+                "Ignoring death link from {} because the player hasn't lit the first bonfire",
+                source
+            );
+            return;
+        }
+        if !self.allow_death_link() {
+            if self.is_death_link_enabled() {
+                self.ignored_death_links += 1;
+            }
+            return;
+        }
 
         let last_death_link_time = SystemTime::now() - self.last_death_link.elapsed();
         match time.duration_since(last_death_link_time) {
-            Ok(dur) if dur < DEATH_LINK_GRACE_PERIOD => return,
+            Ok(dur) if dur >= self.config.death_link_grace_period() => {}
             // An error means that the last death link was *after* [time].
-            Err(_) => return,
-            _ => {}
+            _ => {
+                self.ignored_death_links += 1;
+                return;
+            }
         }
 
         let Ok(player) = (unsafe { PlayerIns::instance() }) else {
             return;
         };
 
-        // Always ignore death links that we sent.
         player.kill();
         self.last_death_link = Instant::now();
+        self.session_stats.death_links_received += 1;
+        self.log(format!(
+            "Killed by a death link: {}",
+            cause.clone().unwrap_or_else(|| format!("{source} died."))
+        ));
+
+        if let Some(template) = self.config.death_link_message() {
+            let message = template
+                .replace("{source}", &source)
+                .replace("{cause}", cause.as_deref().unwrap_or("unknown causes"));
+            self.say(message);
+        }
+    }
+
+    /// Once the death link grace period has ended, logs how many death links
+    /// were ignored during it (if any).
+    fn report_ignored_death_links(&mut self) {
+        if self.ignored_death_links == 0
+            || self.last_death_link.elapsed() < self.config.death_link_grace_period()
+        {
+            return;
+        }
+
+        let count = mem::take(&mut self.ignored_death_links);
+        self.log(format!(
+            "Ignored {} during the death link grace period.",
+            if count == 1 {
+                "1 death link".to_string()
+            } else {
+                format!("{} death links", count)
+            }
+        ));
     }
 
     /// If a shop is currently open, send all its locations as hints to the
     /// server.
     fn send_shop_hints(&mut self) -> Result<()> {
-        let Some(client) = self.connection.client_mut() else {
+        if self.connection.client().is_none() {
             return Ok(());
-        };
+        }
         let Ok(regulation_manager) = (unsafe { CSRegulationManager::instance() }) else {
             return Ok(());
         };
@@ -608,50 +2607,51 @@ impl Core {
                     .archipelago_location_id()
             })
             .collect::<Vec<_>>();
-        if !locations.is_empty() {
-            info!("Hinting location IDs: {:?}", locations);
-            client.create_hints(locations)?;
-        }
-        Ok(())
+        self.scout_locations(locations)
     }
 
     /// Sends a death link notification when the player dies.
     fn send_death_link(&mut self) -> Result<()> {
-        if !self.allow_death_link() {
-            return Ok(());
-        }
-        let Some(client) = self.connection.client_mut() else {
-            return Ok(());
-        };
         let Ok(player) = (unsafe { PlayerIns::instance() }) else {
             return Ok(());
         };
-        if player.super_chr_ins.modules.data.hp != 0 {
+        // HP alone isn't enough, since it can read as zero for a moment while
+        // the player isn't actually dying. Only count it once the game has
+        // also put the character into its dead state.
+        let dead = player.super_chr_ins.modules.data.hp == 0 && player.is_dead();
+
+        // Track this even when death links aren't allowed so that a death
+        // during the grace period doesn't leave a stale timestamp behind.
+        let confirmed = self.death_confirmation.update(dead, Instant::now());
+        if !confirmed || !self.allow_death_link() {
             return Ok(());
         }
+        let Some(client) = self.connection.client_mut() else {
+            return Ok(());
+        };
         let Some(mut save) = SaveData::instance_mut() else {
             return Ok(());
         };
 
-        if client.slot_data().options.death_link != DeathLinkOption::LostSouls
+        if !self.config.send_death_links() {
+            info!("Not sending a death link because sending death links is disabled");
+        } else if client.slot_data().options.death_link != DeathLinkOption::LostSouls
             || unsafe { GameDataMan::instance() }.is_ok_and(|man| man.bloodstain.exists())
         {
-            save.deaths += 1;
             let amnesty = client.slot_data().options.death_link_amnesty;
-            if save.deaths >= amnesty {
-                client.death_link(Default::default())?;
-                save.deaths = 0;
-                self.log("You have sent a death link to your teammates.");
-            } else {
-                let remaining = amnesty - save.deaths;
-                self.log(format!(
+            match report_death(client, &mut save, amnesty)? {
+                DeathReport::Sent => {
+                    self.session_stats.death_links_sent += 1;
+                    self.log("You have sent a death link to your teammates.");
+                }
+                DeathReport::Amnesty { remaining } => self.log(format!(
                     "You have been granted death link amnesty. {}",
                     if remaining == 1 {
                         "1 death remains.".to_string()
                     } else {
                         format!("{} deaths remain.", remaining)
                     }
-                ));
+                )),
             }
         }
 
@@ -666,12 +2666,111 @@ impl Core {
     /// Returns whether death links (sending or receiving) are currently
     /// allowed.
     fn allow_death_link(&self) -> bool {
-        let Some(client) = self.connection.client() else {
+        self.is_death_link_enabled()
+            && !self.death_link_paused
+            && self.is_past_intro()
+            && self.last_death_link.elapsed() >= self.config.death_link_grace_period()
+    }
+
+    /// Returns whether the player has lit the first bonfire, and so is past the
+    /// game's intro. Returns false if the event flags aren't available.
+    fn is_past_intro(&self) -> bool {
+        let Ok(event_man) = (unsafe { SprjEventFlagMan::instance() }) else {
             return false;
         };
+        EventFlag::try_from(FIRST_BONFIRE_FLAG).is_ok_and(|flag| event_man.get_flag(flag))
+    }
+
+    /// Returns how much longer death links will be ignored because one was
+    /// recently sent or received, or None if death links aren't currently
+    /// being ignored for that reason.
+    pub fn remaining_death_link_grace_period(&self) -> Option<Duration> {
+        if !self.is_death_link_enabled() {
+            return None;
+        }
+        self.config
+            .death_link_grace_period()
+            .checked_sub(self.last_death_link.elapsed())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Sets the death link grace period without saving it to the config file.
+    /// This changes continuously while the player drags its slider, so it
+    /// should be saved with [save_config] once they're done.
+    pub fn set_death_link_grace_period(&mut self, period: Duration) {
+        self.config.set_death_link_grace_period(period);
+    }
 
-        client.slot_data().options.death_link != DeathLinkOption::Off
-            && self.last_death_link.elapsed() >= DEATH_LINK_GRACE_PERIOD
+    /// Saves the current configuration to the config file.
+    pub fn save_config(&self) -> Result<()> {
+        self.config.save()
+    }
+
+    /// Sets whether to send death links and saves it to the config file.
+    pub fn set_send_death_links(&mut self, send: bool) -> Result<()> {
+        self.config.set_send_death_links(send);
+        self.config.save()
+    }
+
+    /// Sets whether to receive death links and saves it to the config file.
+    pub fn set_receive_death_links(&mut self, receive: bool) -> Result<()> {
+        self.config.set_receive_death_links(receive);
+        self.config.save()
+    }
+
+    /// Sets whether to play item sound cues and saves it to the config file.
+    pub fn set_item_sound_cues(&mut self, enabled: bool) -> Result<()> {
+        self.config.set_item_sound_cues(enabled);
+        self.config.save()
+    }
+
+    /// Returns whether the player has temporarily paused death link.
+    pub fn is_death_link_paused(&self) -> bool {
+        self.death_link_paused
+    }
+
+    /// Pauses death link if it's running or resumes it if it's paused. This
+    /// only lasts for the current session. Does nothing if death link isn't
+    /// enabled for this slot.
+    pub fn toggle_death_link_paused(&mut self) {
+        if !self.is_death_link_enabled() {
+            return;
+        }
+
+        self.death_link_paused = !self.death_link_paused;
+        self.log(ap::RichText::Color {
+            text: if self.death_link_paused {
+                "Death link paused.".into()
+            } else {
+                "Death link resumed.".into()
+            },
+            color: ap::TextColor::Yellow,
+        });
+    }
+
+    /// Returns whether death link is enabled for this slot at all.
+    fn is_death_link_enabled(&self) -> bool {
+        self.connection
+            .client()
+            .is_some_and(|c| c.slot_data().options.death_link != DeathLinkOption::Off)
+    }
+
+    /// Returns a description of what the player needs to do to achieve their
+    /// goal, or None if the client isn't connected or the goal isn't known.
+    pub fn goal_description(&self) -> Option<&str> {
+        let slot_data = self.connection.client()?.slot_data();
+        if let Some(description) = &slot_data.goal_description {
+            Some(description)
+        } else if slot_data.goal == default_goal() {
+            Some("Defeat the Soul of Cinder")
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the player has achieved their goal in this session.
+    pub fn has_sent_goal(&self) -> bool {
+        self.sent_goal
     }
 
     /// Detects when the player has won the game and notifies the server.
@@ -685,8 +2784,27 @@ impl Core {
                 .iter()
                 .all(|flag| event_man.get_flag(*flag))
         {
-            client.set_status(ap::ClientStatus::Goal)?;
+            let first_time = send_goal(client, SaveData::instance_mut().as_deref_mut())?;
             self.sent_goal = true;
+            if !first_time {
+                return Ok(());
+            }
+
+            if let Some(template) = self.config.goal_message() {
+                let message = template
+                    .replace("{player}", self.config.slot())
+                    .replace("{seed}", self.config.seed());
+                self.say(message);
+            }
+
+            // Whether these are allowed depends on the room's settings. If
+            // they aren't, the server will respond with an explanation.
+            if self.config.release_on_goal() {
+                self.say("!release");
+            }
+            if self.config.collect_on_goal() {
+                self.say("!collect");
+            }
         }
 
         Ok(())
@@ -697,11 +2815,842 @@ impl Core {
     pub fn log(&mut self, message: impl Into<ap::Print>) {
         let print = message.into();
         info!("[APC] {print}");
-        // Consider making this a circular buffer if it ends up eating too much
-        // memory over time.
-        if self.log_buffer.len() >= LOG_BUFFER_LIMIT {
+        self.push_log(print);
+    }
+
+    /// Adds [print] to the log buffer and the persistent log history, if it's
+    /// enabled.
+    fn push_log(&mut self, print: ap::Print) {
+        if let Some(history) = self.log_history.as_mut() {
+            history.append(&print);
+        }
+        while self.log_buffer.len() >= self.config.log_buffer_limit().max(1) {
             self.log_buffer.pop_front();
         }
         self.log_buffer.push_back(print);
+        self.log_count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{MockClient, Sent};
+
+    fn closed() -> ap::Error {
+        ap::Error::WebSocket(tungstenite::Error::ConnectionClosed)
+    }
+
+    fn reset() -> ap::Error {
+        ap::Error::WebSocket(tungstenite::Error::Io(io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            "connection reset",
+        )))
+    }
+
+    fn io_error(kind: io::ErrorKind) -> ap::Error {
+        ap::Error::WebSocket(tungstenite::Error::Io(io::Error::from(kind)))
+    }
+
+    #[test]
+    fn describe_timeout() {
+        let (summary, _) = describe_connection_error(&io_error(io::ErrorKind::TimedOut)).unwrap();
+        assert_eq!(summary, "Connection timed out.");
+    }
+
+    #[test]
+    fn describe_unknown_host() {
+        let err = ap::Error::WebSocket(tungstenite::Error::Io(io::Error::from_raw_os_error(11001)));
+        let (summary, _) = describe_connection_error(&err).unwrap();
+        assert_eq!(summary, "Couldn't find the server.");
+    }
+
+    #[test]
+    fn describe_tls_failure() {
+        let err = ap::Error::WebSocket(tungstenite::Error::Tls(
+            tungstenite::error::TlsError::InvalidDnsName,
+        ));
+        let (summary, _) = describe_connection_error(&err).unwrap();
+        assert_eq!(summary, "Secure connection failed.");
+    }
+
+    #[test]
+    fn describe_invalid_url() {
+        let err = ap::Error::WebSocket(tungstenite::Error::Url(
+            tungstenite::error::UrlError::NoHostName,
+        ));
+        let (summary, _) = describe_connection_error(&err).unwrap();
+        assert_eq!(summary, "Invalid server URL.");
+    }
+
+    #[test]
+    fn describe_other_error() {
+        assert!(describe_connection_error(&closed()).is_none());
+        assert!(describe_connection_error(&reset()).is_none());
+    }
+
+    fn refused(codes: &[&str]) -> ap::Error {
+        ap::Error::ConnectionRefused(codes.iter().map(|code| code.to_string()).collect())
+    }
+
+    #[test]
+    fn describe_refusals() {
+        let summary = |codes: &[&str]| describe_connection_error(&refused(codes)).unwrap().0;
+        assert_eq!(summary(&["InvalidSlot"]), "Unknown slot name.");
+        assert_eq!(summary(&["InvalidPassword"]), "Incorrect password.");
+        assert_eq!(
+            summary(&["InvalidGame"]),
+            "This slot isn't a Dark Souls III world."
+        );
+        assert_eq!(
+            summary(&["IncompatibleVersion"]),
+            "Incompatible server version."
+        );
+        assert_eq!(
+            summary(&["InvalidItemsHandling", "InvalidPassword"]),
+            "Incorrect password."
+        );
+    }
+
+    #[test]
+    fn describe_unknown_refusal() {
+        assert!(describe_connection_error(&refused(&["SomethingNew"])).is_none());
+    }
+
+    #[test]
+    fn refusals_are_not_retryable() {
+        assert!(!is_retryable_connection_error(&refused(&["InvalidSlot"])));
+        assert!(!is_retryable_connection_error(&refused(&[
+            "InvalidPassword"
+        ])));
+        assert!(!is_retryable_connection_error(&refused(&[])));
+    }
+
+    #[test]
+    fn network_errors_are_retryable() {
+        assert!(is_retryable_connection_error(&closed()));
+        assert!(is_retryable_connection_error(&reset()));
+        assert!(is_retryable_connection_error(&io_error(
+            io::ErrorKind::TimedOut
+        )));
+    }
+
+    #[test]
+    fn configuration_errors_are_not_retryable() {
+        assert!(!is_retryable_connection_error(&ap::Error::WebSocket(
+            tungstenite::Error::Url(tungstenite::error::UrlError::NoHostName)
+        )));
+        assert!(!is_retryable_connection_error(&ap::Error::WebSocket(
+            tungstenite::Error::Tls(tungstenite::error::TlsError::InvalidDnsName)
+        )));
+    }
+
+    #[test]
+    fn with_scheme_adds_secure_scheme() {
+        assert_eq!(
+            with_scheme("archipelago.gg:12345", true),
+            "wss://archipelago.gg:12345"
+        );
+    }
+
+    #[test]
+    fn with_scheme_keeps_existing_scheme() {
+        for fallback in [true, false] {
+            assert_eq!(
+                with_scheme("ws://localhost:38281", fallback),
+                "ws://localhost:38281"
+            );
+            assert_eq!(
+                with_scheme("wss://archipelago.gg:12345", fallback),
+                "wss://archipelago.gg:12345"
+            );
+        }
+    }
+
+    #[test]
+    fn with_scheme_without_fallback_keeps_url() {
+        assert_eq!(
+            with_scheme("archipelago.gg:12345", false),
+            "archipelago.gg:12345"
+        );
+    }
+
+    #[test]
+    fn clean_close_while_connected_is_closed_by_server() {
+        assert_eq!(
+            classify_disconnect(ap::ConnectionStateType::Connected, &closed()),
+            DisconnectKind::ClosedByServer
+        );
+    }
+
+    #[test]
+    fn clean_close_while_connecting_is_a_failure() {
+        assert_eq!(
+            classify_disconnect(ap::ConnectionStateType::Connecting, &closed()),
+            DisconnectKind::Failed
+        );
+    }
+
+    #[test]
+    fn network_drop_is_a_failure() {
+        assert_eq!(
+            classify_disconnect(ap::ConnectionStateType::Connected, &reset()),
+            DisconnectKind::Failed
+        );
+    }
+
+    #[test]
+    fn death_needs_confirmation() {
+        let start = Instant::now();
+        let mut death = DeathConfirmation::default();
+        assert!(!death.update(true, start));
+        assert!(!death.update(true, start + Duration::from_millis(499)));
+        assert!(death.update(true, start + DEATH_CONFIRMATION_TIME));
+    }
+
+    #[test]
+    fn transient_death_is_not_confirmed() {
+        let start = Instant::now();
+        let mut death = DeathConfirmation::default();
+        assert!(!death.update(true, start));
+        assert!(!death.update(false, start + Duration::from_millis(300)));
+        // The timer starts over once the player is alive again.
+        assert!(!death.update(true, start + Duration::from_millis(600)));
+        assert!(!death.update(true, start + Duration::from_millis(1000)));
+        assert!(death.update(true, start + Duration::from_millis(1100)));
+    }
+
+    #[test]
+    fn item_effect_from_known_name() {
+        assert_eq!(
+            ItemEffect::from_name("full_heal"),
+            Some(ItemEffect::FullHeal)
+        );
+        assert_eq!(ItemEffect::from_name("kill"), Some(ItemEffect::Kill));
+        assert_eq!(
+            ItemEffect::from_name("warp_to_bonfire"),
+            Some(ItemEffect::WarpToBonfire)
+        );
+    }
+
+    #[test]
+    fn item_effect_from_unknown_name() {
+        assert_eq!(ItemEffect::from_name("level_up"), None);
+        assert_eq!(ItemEffect::from_name(""), None);
+        // Effect names are case-sensitive, like the rest of slot data.
+        assert_eq!(ItemEffect::from_name("Full_Heal"), None);
+    }
+
+    fn save_with_locations(locations: impl IntoIterator<Item = i64>) -> SaveData {
+        SaveData {
+            seed: Some("seed".to_string()),
+            locations: locations.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pending_items_include_deferred_traps() {
+        let mut client = MockClient::new("seed", []);
+        client.received_items = 5;
+        let save = SaveData {
+            items_granted: 3,
+            deferred_traps: vec![1],
+            ..Default::default()
+        };
+        assert_eq!(pending_items(&client, &save), 3);
+
+        // A save that's ahead of the server (for example because the room was
+        // reset) doesn't underflow.
+        client.received_items = 1;
+        assert_eq!(pending_items(&client, &save), 1);
+    }
+
+    #[test]
+    fn sends_new_locations_once() {
+        let start = Instant::now();
+        let mut client = MockClient::new("seed", [1, 2, 3]);
+        let mut save = save_with_locations([1]);
+        let mut sender = LocationSender::default();
+
+        sender.sync(&mut client, &mut save, start).unwrap();
+        assert_eq!(client.sent_locations(), vec![vec![1]]);
+
+        // Syncing again before the resend interval doesn't send anything new.
+        sender
+            .sync(&mut client, &mut save, start + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(client.sent_locations(), vec![vec![1]]);
+
+        save.locations.insert(2);
+        sender
+            .sync(&mut client, &mut save, start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(client.sent_locations(), vec![vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn skips_locations_outside_this_world() {
+        let mut client = MockClient::new("seed", [1]);
+        let mut save = save_with_locations([1, 100]);
+        let mut sender = LocationSender::default();
+
+        sender.sync(&mut client, &mut save, Instant::now()).unwrap();
+        assert_eq!(client.sent_locations(), vec![vec![1]]);
+    }
+
+    #[test]
+    fn acknowledgement_reports_round_trip() {
+        let start = Instant::now();
+        let mut client = MockClient::new("seed", [1]);
+        let mut save = save_with_locations([1]);
+        let mut sender = LocationSender::default();
+
+        assert_eq!(sender.sync(&mut client, &mut save, start).unwrap(), None);
+        client.acknowledge();
+        let round_trip = sender
+            .sync(&mut client, &mut save, start + Duration::from_millis(150))
+            .unwrap();
+        assert_eq!(round_trip, Some(Duration::from_millis(150)));
+        assert!(save.acknowledged_locations.contains(&1));
+    }
+
+    #[test]
+    fn resends_unacknowledged_locations_with_backoff() {
+        let start = Instant::now();
+        let mut client = MockClient::new("seed", [1]);
+        let mut save = save_with_locations([1]);
+        let mut sender = LocationSender::default();
+        sender.sync(&mut client, &mut save, start).unwrap();
+
+        let mut now = start;
+        for attempt in 0..MAX_LOCATION_RESENDS {
+            let interval = LOCATION_RESEND_INTERVAL * 2u32.pow(attempt);
+            sender
+                .sync(
+                    &mut client,
+                    &mut save,
+                    now + interval - Duration::from_millis(1),
+                )
+                .unwrap();
+            assert_eq!(client.sent_locations().len(), attempt as usize + 1);
+
+            now += interval;
+            sender.sync(&mut client, &mut save, now).unwrap();
+            assert_eq!(client.sent_locations().len(), attempt as usize + 2);
+        }
+
+        // Once it's given up, it doesn't resend until the next connection.
+        sender
+            .sync(&mut client, &mut save, now + Duration::from_secs(3600))
+            .unwrap();
+        assert_eq!(
+            client.sent_locations().len(),
+            MAX_LOCATION_RESENDS as usize + 1
+        );
+    }
+
+    #[test]
+    fn death_link_waits_for_amnesty() {
+        let mut client = MockClient::new("seed", []);
+        let mut save = SaveData::default();
+
+        assert_eq!(
+            report_death(&mut client, &mut save, 3).unwrap(),
+            DeathReport::Amnesty { remaining: 2 }
+        );
+        assert_eq!(
+            report_death(&mut client, &mut save, 3).unwrap(),
+            DeathReport::Amnesty { remaining: 1 }
+        );
+        assert!(client.sent.is_empty());
+
+        assert_eq!(
+            report_death(&mut client, &mut save, 3).unwrap(),
+            DeathReport::Sent
+        );
+        assert_eq!(
+            client.sent,
+            vec![Sent::DeathLink("Ashen One died.".to_string())]
+        );
+        assert_eq!(save.deaths, 0);
+    }
+
+    #[test]
+    fn death_link_without_amnesty() {
+        let mut client = MockClient::new("seed", []);
+        let mut save = SaveData::default();
+
+        assert_eq!(
+            report_death(&mut client, &mut save, 1).unwrap(),
+            DeathReport::Sent
+        );
+        assert_eq!(client.sent.len(), 1);
+    }
+
+    #[test]
+    fn goal_is_announced_once_per_save() {
+        let mut client = MockClient::new("seed", []);
+        let mut save = SaveData::default();
+
+        assert!(send_goal(&mut client, Some(&mut save)).unwrap());
+        assert!(save.announced_goal);
+
+        // The status is sent again, but it's no longer the first time.
+        assert!(!send_goal(&mut client, Some(&mut save)).unwrap());
+        assert_eq!(client.sent, vec![Sent::Goal, Sent::Goal]);
+
+        // Without a save there's no way to know, so assume it's not new.
+        assert!(!send_goal(&mut client, None).unwrap());
+    }
+
+    #[test]
+    fn matching_seeds_do_not_conflict() {
+        let client = MockClient::new("seed", []);
+        assert!(seed_conflict(Some(client.seed_name()), Some("seed"), "seed").is_ok());
+        assert!(seed_conflict(None, Some("seed"), "seed").is_ok());
+        assert!(seed_conflict(Some(client.seed_name()), None, "seed").is_ok());
+        assert!(seed_conflict(None, None, "seed").is_ok());
+    }
+
+    #[test]
+    fn room_seed_conflicts_with_config() {
+        let client = MockClient::new("other", []);
+        let err = seed_conflict(Some(client.seed_name()), None, "seed").unwrap_err();
+        assert!(err.to_string().contains("DS3Randomizer.exe used"));
+    }
+
+    #[test]
+    fn room_seed_conflicts_with_save() {
+        // The config matches the room but not the save, as if the player
+        // loaded a save from a different multiworld.
+        let client = MockClient::new("seed", []);
+        let err = seed_conflict(Some(client.seed_name()), Some("other"), "seed").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Connected room seed: seed"));
+        assert!(message.contains("Save file seed: other"));
+    }
+
+    #[test]
+    fn config_seed_conflicts_with_save() {
+        let err = seed_conflict(None, Some("other"), "seed").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Your most recent DS3Randomizer.exe")
+        );
+    }
+
+    const ITEM_INTERVAL: Duration = Duration::from_secs(1);
+    const TRAP_INTERVAL: Duration = Duration::from_secs(10);
+
+    fn grant_times(start: Instant) -> GrantTimes {
+        GrantTimes {
+            last_item: start - ITEM_INTERVAL,
+            last_trap: None,
+            item_interval: ITEM_INTERVAL,
+            trap_interval: TRAP_INTERVAL,
+        }
+    }
+
+    /// Runs [next_item_action] every 100ms for [duration] over items whose
+    /// trap status is given by [traps], applying each action the way
+    /// [Core::process_incoming_items] does, and returns the index and time
+    /// of each item granted.
+    fn simulate_grants(traps: &[bool], duration: Duration) -> Vec<(usize, Duration)> {
+        let start = Instant::now();
+        let mut times = grant_times(start);
+        let mut items_granted = 0;
+        let mut deferred_traps = vec![];
+        let mut granted = vec![];
+        let mut elapsed = Duration::ZERO;
+        while elapsed <= duration {
+            let now = start + elapsed;
+            let action = next_item_action(
+                items_granted,
+                &deferred_traps,
+                None,
+                |index| traps.get(index).copied(),
+                times,
+                now,
+            );
+            let index = match action {
+                ItemAction::Wait => None,
+                ItemAction::Defer(index) => {
+                    deferred_traps.push(index);
+                    items_granted += 1;
+                    None
+                }
+                ItemAction::Grant(index) => {
+                    items_granted += 1;
+                    Some(index)
+                }
+                ItemAction::GrantDeferred(index) => {
+                    deferred_traps.remove(0);
+                    Some(index)
+                }
+            };
+            if let Some(index) = index {
+                granted.push((index, elapsed));
+                times.last_item = now;
+                if traps[index] {
+                    times.last_trap = Some(now);
+                }
+            }
+            elapsed += Duration::from_millis(100);
+        }
+        granted
+    }
+
+    #[test]
+    fn items_wait_for_item_interval() {
+        let start = Instant::now();
+        let mut times = grant_times(start);
+        times.last_item = start;
+        let is_trap = |index| [false, false].get(index).copied();
+
+        assert_eq!(
+            next_item_action(0, &[], None, is_trap, times, start),
+            ItemAction::Wait
+        );
+        assert_eq!(
+            next_item_action(0, &[], None, is_trap, times, start + ITEM_INTERVAL),
+            ItemAction::Grant(0)
+        );
+    }
+
+    #[test]
+    fn trap_is_deferred_until_trap_interval() {
+        let start = Instant::now();
+        let mut times = grant_times(start);
+        times.last_trap = Some(start - ITEM_INTERVAL);
+        let is_trap = |index| [true, false].get(index).copied();
+
+        assert_eq!(
+            next_item_action(0, &[], None, is_trap, times, start),
+            ItemAction::Defer(0)
+        );
+        assert_eq!(
+            next_item_action(1, &[0], None, is_trap, times, start),
+            ItemAction::Grant(1)
+        );
+        assert_eq!(
+            next_item_action(2, &[0], None, is_trap, times, start + TRAP_INTERVAL),
+            ItemAction::GrantDeferred(0)
+        );
+    }
+
+    #[test]
+    fn ceiling_holds_back_items_but_not_deferred_traps() {
+        let start = Instant::now();
+        let times = grant_times(start);
+        let is_trap = |index| [true, false].get(index).copied();
+
+        assert_eq!(
+            next_item_action(1, &[], Some(1), is_trap, times, start),
+            ItemAction::Wait
+        );
+        assert_eq!(
+            next_item_action(1, &[0], Some(1), is_trap, times, start),
+            ItemAction::GrantDeferred(0)
+        );
+    }
+
+    #[test]
+    fn interleaved_traps_respect_both_intervals() {
+        let traps = [false, true, true, false, true, false, false, true, false];
+        let granted = simulate_grants(&traps, Duration::from_secs(60));
+
+        // Every item is eventually granted exactly once.
+        let mut indices = granted.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        indices.sort();
+        assert_eq!(indices, (0..traps.len()).collect::<Vec<_>>());
+
+        for pair in granted.windows(2) {
+            assert!(pair[1].1 - pair[0].1 >= ITEM_INTERVAL, "{granted:?}");
+        }
+
+        let trap_times = granted
+            .iter()
+            .filter(|(index, _)| traps[*index])
+            .map(|(_, time)| *time)
+            .collect::<Vec<_>>();
+        for pair in trap_times.windows(2) {
+            assert!(pair[1] - pair[0] >= TRAP_INTERVAL, "{granted:?}");
+        }
+
+        // Deferred traps don't hold back the normal items after them, so all
+        // of those are granted at the item interval.
+        let normal_times = granted
+            .iter()
+            .filter(|(index, _)| !traps[*index])
+            .map(|(_, time)| *time)
+            .collect::<Vec<_>>();
+        assert!(
+            normal_times.last().unwrap() <= &Duration::from_secs(traps.len() as u64),
+            "{granted:?}"
+        );
+    }
+
+    #[test]
+    fn acknowledged_locations_are_not_unacknowledged() {
+        let mut client = MockClient::new("seed", [2, 3]);
+        client.checked.insert(1);
+        let mut save = save_with_locations([1, 2, 100]);
+
+        assert_eq!(unacknowledged_locations(&client, &mut save), vec![2]);
+        assert_eq!(save.acknowledged_locations, HashSet::from([1]));
+    }
+
+    #[test]
+    fn acknowledged_locations_are_not_resent_after_reconnect() {
+        let start = Instant::now();
+        let mut client = MockClient::new("seed", [1, 2]);
+        let mut save = save_with_locations([1, 2]);
+        let mut sender = LocationSender::default();
+
+        sender.sync(&mut client, &mut save, start).unwrap();
+        client.acknowledge();
+        sender
+            .sync(&mut client, &mut save, start + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(save.acknowledged_locations, HashSet::from([1, 2]));
+
+        // Reconnecting forgets what was sent over the old connection, but the
+        // save still records what the server acknowledged.
+        sender.reset();
+        sender
+            .sync(&mut client, &mut save, start + Duration::from_secs(2))
+            .unwrap();
+        sender
+            .sync(&mut client, &mut save, start + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(client.sent_locations().len(), 1);
+    }
+
+    #[test]
+    fn unacknowledged_locations_are_resent_after_reconnect() {
+        let start = Instant::now();
+        let mut client = MockClient::new("seed", [1, 2]);
+        let mut save = save_with_locations([1]);
+        let mut sender = LocationSender::default();
+
+        sender.sync(&mut client, &mut save, start).unwrap();
+        client.acknowledge();
+        save.locations.insert(2);
+        sender
+            .sync(&mut client, &mut save, start + Duration::from_secs(1))
+            .unwrap();
+
+        // The connection drops before the server acknowledges location 2.
+        sender.reset();
+        sender
+            .sync(&mut client, &mut save, start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(client.sent_locations(), vec![vec![1], vec![2], vec![2]]);
+    }
+
+    #[test]
+    fn matching_seed_allows_grants() {
+        let client = MockClient::new("seed", []);
+        assert!(seed_matches(&client, &save_with_locations([])));
+    }
+
+    #[test]
+    fn conflicting_seed_blocks_grants() {
+        let client = MockClient::new("other", []);
+        assert!(!seed_matches(&client, &save_with_locations([])));
+    }
+
+    #[test]
+    fn unresolved_seed_blocks_grants() {
+        // A save that hasn't been associated with any seed yet might belong to
+        // a different multiworld, so it mustn't be granted items either.
+        let client = MockClient::new("seed", []);
+        assert!(!seed_matches(&client, &SaveData::default()));
+    }
+
+    /// Returns slot data with the required fields filled in and the fields in
+    /// [extra] added.
+    fn slot_data(extra: json::Value) -> SlotData {
+        let mut data = json::json!({
+            "apIdsToItemIds": {},
+            "itemCounts": {},
+            "options": {"death_link": 0, "enable_dlc": 0},
+        });
+        data.as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        // Go through a string because the map keys can only be deserialized
+        // from borrowed strings.
+        json::from_str(&data.to_string()).unwrap()
+    }
+
+    fn goods(param_id: u32) -> ItemId {
+        (0x40000000 | param_id).try_into().unwrap()
+    }
+
+    #[test]
+    fn gesture_from_slot_data() {
+        let slot_data = slot_data(json::json!({"gestureItems": {"2140": 34}}));
+        assert_eq!(Core::gesture_for(&slot_data, goods(2140)), Some(34));
+
+        // Only goods can be gestures.
+        let weapon: ItemId = 2140u32.try_into().unwrap();
+        assert_eq!(Core::gesture_for(&slot_data, weapon), None);
+        assert_eq!(Core::gesture_for(&slot_data, goods(2141)), None);
+    }
+
+    #[test]
+    fn native_gesture_without_slot_data() {
+        let slot_data = slot_data(json::json!({}));
+        assert_eq!(
+            Core::gesture_for(&slot_data, goods(9030)),
+            Some(PATH_OF_THE_DRAGON_GESTURE)
+        );
+    }
+
+    #[test]
+    fn item_without_effect_is_granted() {
+        let slot_data = slot_data(json::json!({"itemEffects": {"2": "kill"}}));
+        assert_eq!(
+            dispatch_item(&slot_data, 1),
+            ItemDispatch {
+                effect: None,
+                grant: true
+            }
+        );
+    }
+
+    #[test]
+    fn known_effect_runs_instead_of_granting() {
+        let slot_data = slot_data(json::json!({
+            "itemEffects": {"1": "full_heal", "2": "kill", "3": "warp_to_bonfire"},
+        }));
+        for (ap_id, effect) in [
+            (1, ItemEffect::FullHeal),
+            (2, ItemEffect::Kill),
+            (3, ItemEffect::WarpToBonfire),
+        ] {
+            assert_eq!(
+                dispatch_item(&slot_data, ap_id),
+                ItemDispatch {
+                    effect: Some(effect),
+                    grant: false
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn known_effect_with_ds3_item_is_also_granted() {
+        let slot_data = slot_data(json::json!({
+            "apIdsToItemIds": {"1": 0x40000000 + 240},
+            "itemEffects": {"1": "full_heal"},
+        }));
+        assert_eq!(
+            dispatch_item(&slot_data, 1),
+            ItemDispatch {
+                effect: Some(ItemEffect::FullHeal),
+                grant: true
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_effect_is_skipped() {
+        let slot_data = slot_data(json::json!({"itemEffects": {"1": "level_up"}}));
+        assert_eq!(
+            dispatch_item(&slot_data, 1),
+            ItemDispatch {
+                effect: None,
+                grant: false
+            }
+        );
+    }
+
+    fn config_for(url: &str, password: Option<&str>) -> Config {
+        let mut config = Config::default();
+        config.set_url(url);
+        config.set_password(password);
+        config
+    }
+
+    #[test]
+    fn resubmitting_same_url_keeps_connection() {
+        let config = config_for("archipelago.gg:12345", None);
+        assert!(is_current_connection(
+            &config,
+            "archipelago.gg:12345",
+            None,
+            false
+        ));
+        // The password field submits an empty string when it's left blank.
+        assert!(is_current_connection(
+            &config,
+            "archipelago.gg:12345",
+            Some(""),
+            false
+        ));
+
+        let config = config_for("archipelago.gg:12345", Some("hunter2"));
+        assert!(is_current_connection(
+            &config,
+            "archipelago.gg:12345",
+            Some("hunter2"),
+            false
+        ));
+    }
+
+    #[test]
+    fn resubmitting_same_url_while_disconnected_reconnects() {
+        let config = config_for("archipelago.gg:12345", None);
+        assert!(!is_current_connection(
+            &config,
+            "archipelago.gg:12345",
+            None,
+            true
+        ));
+    }
+
+    #[test]
+    fn changed_url_or_password_reconnects() {
+        let config = config_for("archipelago.gg:12345", Some("hunter2"));
+        assert!(!is_current_connection(
+            &config,
+            "archipelago.gg:54321",
+            Some("hunter2"),
+            false
+        ));
+        assert!(!is_current_connection(
+            &config,
+            "archipelago.gg:12345",
+            Some("hunter3"),
+            false
+        ));
+        assert!(!is_current_connection(
+            &config,
+            "archipelago.gg:12345",
+            None,
+            false
+        ));
+    }
+
+    #[test]
+    fn round_trip_excludes_resend_backoff() {
+        let start = Instant::now();
+        let mut client = MockClient::new("seed", [1]);
+        let mut save = save_with_locations([1]);
+        let mut sender = LocationSender::default();
+        sender.sync(&mut client, &mut save, start).unwrap();
+
+        let resend = start + LOCATION_RESEND_INTERVAL;
+        sender.sync(&mut client, &mut save, resend).unwrap();
+        assert_eq!(client.sent_locations().len(), 2);
+
+        client.acknowledge();
+        let round_trip = sender
+            .sync(&mut client, &mut save, resend + Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(round_trip, Some(Duration::from_millis(200)));
     }
 }
@@ -9,7 +9,8 @@ use darksouls3_extra::input::{InputBlocker, InputFlags};
 use fromsoftware_shared::FromStatic;
 
 use crate::{
-    Core, clipboard_backend::WindowsClipboardBackend, overlay::Overlay, utils::PopupModalExt,
+    Core, clipboard_backend::WindowsClipboardBackend, core::SeedConflict, overlay::Overlay,
+    utils::PopupModalExt,
 };
 
 /// A wrapper around the rest of the mod's UI that doesn't expect any state to
@@ -17,8 +18,10 @@ use crate::{
 /// still using Hudhook and ImGui to surface fatal errors that may occur during
 /// initialization.
 pub struct ErrorDisplay {
-    /// The struct that's used to block and unblock input going to DS3.
-    input_blocker: &'static InputBlocker,
+    /// The struct that's used to block and unblock input going to DS3, or None
+    /// if it failed to initialize. In that case, the overlay still works but
+    /// input isn't blocked while the player interacts with it.
+    input_blocker: Option<&'static InputBlocker>,
 
     /// The main overlay if it managed to initialize correctly, or [None]
     /// otherwise.
@@ -38,7 +41,10 @@ pub struct ErrorDisplay {
 
 impl ErrorDisplay {
     /// Creates a new [ErrorDisplay] that will only ever be run
-    pub fn new(core: Result<Arc<Mutex<Core>>>, input_blocker: &'static InputBlocker) -> Self {
+    pub fn new(
+        core: Result<Arc<Mutex<Core>>>,
+        input_blocker: Option<&'static InputBlocker>,
+    ) -> Self {
         match core {
             Ok(core) => Self {
                 input_blocker,
@@ -61,8 +67,12 @@ impl ErrorDisplay {
 impl ImguiRenderLoop for ErrorDisplay {
     fn render(&mut self, ui: &mut Ui) {
         let io = ui.io();
+        let captures_mouse = self
+            .overlay
+            .as_ref()
+            .is_none_or(|overlay| overlay.captures_mouse());
         let mut flag = InputFlags::empty();
-        if io.want_capture_mouse {
+        if io.want_capture_mouse && captures_mouse {
             flag |= InputFlags::Mouse;
         }
         if io.want_capture_keyboard {
@@ -73,7 +83,9 @@ impl ImguiRenderLoop for ErrorDisplay {
             // (for example if a modal dialog is up).
             flag |= InputFlags::GamePad;
         }
-        self.input_blocker.block_only(flag);
+        if let Some(input_blocker) = self.input_blocker {
+            input_blocker.block_only(flag);
+        }
 
         if let Some(core) = &mut self.core {
             let mut core = core.lock().unwrap();
@@ -87,6 +99,8 @@ impl ImguiRenderLoop for ErrorDisplay {
         }
 
         let Some(error) = &self.error else { return };
+        let is_seed_conflict = error.is::<SeedConflict>();
+        let mut recheck = false;
 
         // Make sure the cursor is visible even if the player is loaded into a
         // save with the menu closed.
@@ -122,7 +136,24 @@ impl ImguiRenderLoop for ErrorDisplay {
                 if ui.button("Exit") {
                     std::process::exit(1);
                 }
+
+                // Seed conflicts can be resolved by loading a different save,
+                // so give the player a way to confirm that they've done so
+                // without waiting for the next check.
+                if is_seed_conflict {
+                    ui.same_line();
+                    if ui.button("Re-check") {
+                        recheck = true;
+                    }
+                }
             });
+
+        if recheck && let Some(core) = &self.core {
+            match core.lock().unwrap().recheck_seed_conflict() {
+                Ok(()) => self.error = None,
+                Err(err) => self.error = Some(err.into()),
+            }
+        }
     }
 
     fn initialize<'a>(&'a mut self, ctx: &mut Context, _render_context: &'a mut dyn RenderContext) {
@@ -1,4 +1,4 @@
-use darksouls3::param::{EQUIP_PARAM_GOODS_ST, EquipParam};
+use darksouls3::param::*;
 use darksouls3::sprj::CSRegulationManager;
 use darksouls3::sprj::{ItemBuffer, ItemCategory, ItemId, MAP_ITEM_MAN_GRANT_ITEM_VA};
 use fromsoftware_shared::FromStatic;
@@ -34,15 +34,33 @@ fn on_grant_items(items: &mut ItemBuffer) {
     for item in items.iter_mut() {
         info!("Received {}x {:?}", item.quantity, item.id);
 
-        if item.id.category() != ItemCategory::Goods || item.id.param_id() <= 3780000 {
+        if !item.id.is_archipelago() {
             // This is a vanilla item.
             continue;
         }
 
-        // Replace placeholders with their real equivalents.
-        let row = &unsafe { CSRegulationManager::instance() }
-            .expect("CSRegulationManager should be available in on_grant_items")
-            .get_param::<EQUIP_PARAM_GOODS_ST>()[item.id.param_id().into()];
+        // Replace placeholders with their real equivalents. Placeholders are
+        // usually goods, but handle any category in case the static
+        // randomizer needs to use another.
+        let regulation_manager = unsafe { CSRegulationManager::instance() }
+            .expect("CSRegulationManager should be available in on_grant_items");
+        let Some(row) = regulation_manager.get_equip_param(item.id) else {
+            warn!(
+                "  No {:?} row defined for Archipelago ID {:?}",
+                item.id.category(),
+                item.id
+            );
+            continue;
+        };
+        let row = row.as_dyn();
+        if !row.is_archipelago() {
+            warn!(
+                "  {:?} is in the Archipelago ID range but has no Archipelago location",
+                item.id
+            );
+            continue;
+        }
+
         if let Some((real_id, quantity)) = row.archipelago_item() {
             info!("  Archipelago location: {}", row.archipelago_location_id());
             info!("  Converting to {}x {:?}", quantity, real_id);
@@ -58,7 +76,8 @@ fn on_grant_items(items: &mut ItemBuffer) {
             item.durability = -1;
         } else {
             info!(
-                "  Item has no Archipelago metadata. Basic price: {}, sell value: {}",
+                "  {:?} item has no Archipelago metadata. Basic price: {}, sell value: {}",
+                item.id.category(),
                 row.basic_price(),
                 row.sell_value()
             );
@@ -66,10 +85,30 @@ fn on_grant_items(items: &mut ItemBuffer) {
     }
 }
 
+/// The ID of the gesture granted by the Path of the Dragon item.
+pub const PATH_OF_THE_DRAGON_GESTURE: u32 = 29;
+
+/// Goods param IDs for items that represent gestures, along with the IDs of the
+/// gestures they grant. The player should be given the gesture itself rather
+/// than the (useless) item.
+const GESTURE_GOODS: &[(u32, u32)] = &[
+    // Path of the Dragon
+    (9030, PATH_OF_THE_DRAGON_GESTURE),
+];
+
 pub trait ItemIdExt {
-    /// Returns whether this ID represents an item added specifically for
-    /// Archipelago.
+    /// Returns whether this ID is in the range used for items added
+    /// specifically for Archipelago.
+    ///
+    /// This is only a heuristic, since nothing stops future game updates or
+    /// other mods from using IDs in this range. Callers should also check
+    /// [EquipParamExt::is_archipelago] on the item's param row before treating
+    /// it as an Archipelago item.
     fn is_archipelago(&self) -> bool;
+
+    /// If this ID represents a vanilla item that grants a gesture, returns the
+    /// ID of that gesture.
+    fn gesture_id(&self) -> Option<u32>;
 }
 
 impl ItemIdExt for ItemId {
@@ -83,9 +122,25 @@ impl ItemIdExt for ItemId {
             Accessory | Goods => id > 3780000,
         }
     }
+
+    fn gesture_id(&self) -> Option<u32> {
+        if self.category() != ItemCategory::Goods {
+            return None;
+        }
+
+        GESTURE_GOODS
+            .iter()
+            .find(|(goods_id, _)| *goods_id == self.param_id())
+            .map(|(_, gesture_id)| *gesture_id)
+    }
 }
 
 pub trait EquipParamExt {
+    /// Returns whether this param row was created for Archipelago. The static
+    /// randomizer always encodes a location ID in Archipelago rows, so rows
+    /// without one are vanilla (or from another mod).
+    fn is_archipelago(&self) -> bool;
+
     /// Returns the Archipelago location ID encoded in this item's unused
     /// params.
     fn archipelago_location_id(&self) -> i64;
@@ -97,9 +152,15 @@ pub trait EquipParamExt {
 }
 
 impl<T: ?Sized + EquipParam> EquipParamExt for T {
+    fn is_archipelago(&self) -> bool {
+        self.archipelago_location_id() != 0
+    }
+
     fn archipelago_location_id(&self) -> i64 {
-        self.vagrant_item_lot_id() as i64
-            + ((self.vagrant_bonus_ene_drop_item_lot_id() as i64) << 32)
+        location_id_from_params(
+            self.vagrant_item_lot_id() as i64,
+            self.vagrant_bonus_ene_drop_item_lot_id() as i64,
+        )
     }
 
     fn archipelago_item(&self) -> Option<(ItemId, u32)> {
@@ -121,3 +182,65 @@ impl<T: ?Sized + EquipParam> EquipParamExt for T {
         }
     }
 }
+
+/// Decodes the Archipelago location ID that the static randomizer splits across
+/// two unused params, with [low] holding the low 32 bits and [high] holding the
+/// high 32 bits. This is zero for rows that don't represent Archipelago items.
+fn location_id_from_params(low: i64, high: i64) -> i64 {
+    low + (high << 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the ID of the item in [category] with the given [param_id].
+    fn item_id(category: u32, param_id: u32) -> ItemId {
+        (category | param_id).try_into().unwrap()
+    }
+
+    const WEAPON: u32 = 0x00000000;
+    const PROTECTOR: u32 = 0x10000000;
+    const ACCESSORY: u32 = 0x20000000;
+    const GOODS: u32 = 0x40000000;
+
+    #[test]
+    fn vanilla_ids_are_not_archipelago() {
+        assert!(!item_id(WEAPON, 23010000).is_archipelago());
+        assert!(!item_id(PROTECTOR, 99003000).is_archipelago());
+        assert!(!item_id(ACCESSORY, 3780000).is_archipelago());
+        assert!(!item_id(GOODS, 3780000).is_archipelago());
+        assert!(!item_id(GOODS, 9030).is_archipelago());
+    }
+
+    #[test]
+    fn ids_above_thresholds_are_archipelago() {
+        assert!(item_id(WEAPON, 23010001).is_archipelago());
+        assert!(item_id(PROTECTOR, 99003001).is_archipelago());
+        assert!(item_id(ACCESSORY, 3780001).is_archipelago());
+        assert!(item_id(GOODS, 3780001).is_archipelago());
+    }
+
+    #[test]
+    fn gesture_ids() {
+        assert_eq!(
+            item_id(GOODS, 9030).gesture_id(),
+            Some(PATH_OF_THE_DRAGON_GESTURE)
+        );
+        assert_eq!(item_id(WEAPON, 9030).gesture_id(), None);
+        assert_eq!(item_id(GOODS, 9031).gesture_id(), None);
+    }
+
+    #[test]
+    fn missing_location_id_is_zero() {
+        // Rows without an encoded location aren't Archipelago rows.
+        assert_eq!(location_id_from_params(0, 0), 0);
+    }
+
+    #[test]
+    fn location_id_combines_params() {
+        assert_eq!(location_id_from_params(1234, 0), 1234);
+        assert_eq!(location_id_from_params(0, 1), 1 << 32);
+        assert_eq!(location_id_from_params(5, 2), (2 << 32) + 5);
+    }
+}
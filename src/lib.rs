@@ -1,9 +1,8 @@
-use std::sync::{Arc, Mutex};
-use std::{fs, panic, path::Path, time::Duration};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::{panic, path::Path, time::Duration};
 
 use anyhow::Result;
 use backtrace::Backtrace;
-use chrono::prelude::*;
 use darksouls3::sprj::{SprjTaskGroupIndex, SprjTaskImp};
 use darksouls3::util::system::wait_for_system_init;
 use darksouls3_extra::input::InputBlocker;
@@ -18,39 +17,63 @@ use windows::core::*;
 
 use crate::core::Core;
 
+mod client;
 mod clipboard_backend;
 mod config;
+mod connection;
 mod core;
 mod error_display;
 mod item;
+mod log_file;
+mod log_history;
 mod overlay;
 mod save_data;
 mod slot_data;
+mod sound;
 mod utils;
 
 use error_display::ErrorDisplay;
+use log_file::LogFileWriter;
 use save_data::SaveData;
 
+/// The mod's [Core], once it's been created. This is only used to let it know
+/// when the game is shutting down; everything else gets its own reference.
+static CORE: OnceLock<Arc<Mutex<Core>>> = OnceLock::new();
+
 /// The entrypoint called when the DLL is first loaded.
 ///
 /// This is where we set up the whole mod and start waiting for the app itself
 /// to be initialized enough for us to start doing real things.
 #[unsafe(no_mangle)]
 extern "C" fn DllMain(hmodule: HINSTANCE, call_reason: u32) -> bool {
-    if call_reason != DLL_PROCESS_ATTACH {
+    if call_reason == DLL_PROCESS_DETACH {
+        shut_down();
+        return true;
+    } else if call_reason != DLL_PROCESS_ATTACH {
         return true;
     }
 
     handle_panics();
 
-    // If there's an error locating the mod directory, try to log to the current
-    // dir instead. Otherwise, ignore the error so we can surface it better
-    // throught he UI.
-    if let Ok(dir) = utils::mod_directory() {
-        let _ = start_logger(dir);
-        info!("Logger initialized.");
+    // If we've been loaded into some other game, none of the DS3-specific
+    // hooks or singletons will work, so bail out before touching them.
+    if !utils::is_dark_souls_3() {
+        message_box(format!(
+            "The Dark Souls III Archipelago client was loaded into {}, which isn't \
+             DarkSoulsIII.exe. Check that your me3 profile is launching the right game.",
+            std::env::current_exe()
+                .ok()
+                .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| "an unknown process".to_string())
+        ));
+        return true;
     }
 
+    // If there's an error starting the logger, ignore it so we can surface it
+    // better through the UI.
+    let _ = start_logger();
+    info!("Logger initialized.");
+
     // Set up hooks in the main thread to mitigate the risk of the game code
     // executing them while they're being modified.
 
@@ -60,8 +83,15 @@ extern "C" fn DllMain(hmodule: HINSTANCE, call_reason: u32) -> bool {
         item::hook_items();
     }
 
-    let blocker =
-        unsafe { InputBlocker::get_instance() }.expect("Failed to initialize input blocker");
+    // The overlay is still usable without input blocking, so don't let a
+    // failure here take down the whole mod.
+    let blocker = match unsafe { InputBlocker::get_instance() } {
+        Ok(blocker) => Some(blocker),
+        Err(err) => {
+            warn!("Failed to initialize input blocker, game input won't be blocked: {err:?}");
+            None
+        }
+    };
 
     std::thread::spawn(move || {
         info!("Worker thread initialized.");
@@ -78,6 +108,7 @@ extern "C" fn DllMain(hmodule: HINSTANCE, call_reason: u32) -> bool {
         let core = Core::new().map(|core| Arc::new(Mutex::new(core)));
 
         if let Ok(core) = core.as_ref() {
+            let _ = CORE.set(core.clone());
             let core = core.clone();
             unsafe { SprjTaskImp::instance() }
                 .expect("DS3 task runner should be available")
@@ -100,6 +131,20 @@ extern "C" fn DllMain(hmodule: HINSTANCE, call_reason: u32) -> bool {
     true
 }
 
+/// Lets the [Core] know that the DLL is being unloaded, usually because the
+/// game is exiting.
+///
+/// This runs under the loader lock, possibly after the game's other threads
+/// have been stopped, so it mustn't wait on anything. If the core is locked or
+/// poisoned, it's skipped.
+fn shut_down() {
+    if let Some(core) = CORE.get()
+        && let Ok(mut core) = core.try_lock()
+    {
+        core.shut_down();
+    }
+}
+
 /// Handle panics by both logging and popping up a message box, which is the
 /// most reliable way to make something visible to the end user.
 fn handle_panics() {
@@ -122,14 +167,27 @@ fn handle_panics() {
 
 /// Starts the logger which logs to both stdout and a file which users can send
 /// to the devs for debugging.
-fn start_logger(dir: impl AsRef<Path>) -> Result<()> {
+///
+/// Logs are written to the mod directory if possible. If it can't be located
+/// or isn't writable (for example because it's in a protected location), they're
+/// written to the temp directory or the current directory instead.
+fn start_logger() -> Result<()> {
     let mut loggers: Vec<Box<dyn SharedLogger>> = vec![TermLogger::new(
         LevelFilter::Warn,
         simplelog::Config::default(),
         TerminalMode::Mixed,
         ColorChoice::Auto,
     )];
-    if let Ok(logger) = create_write_logger(dir) {
+    let dirs = [
+        utils::mod_directory().ok().map(Path::to_path_buf),
+        Some(std::env::temp_dir().join("ds3-archipelago")),
+        std::env::current_dir().ok(),
+    ];
+    if let Some(logger) = dirs
+        .into_iter()
+        .flatten()
+        .find_map(|dir| create_write_logger(dir).ok())
+    {
         loggers.push(logger);
     }
     CombinedLogger::init(loggers)?;
@@ -137,17 +195,11 @@ fn start_logger(dir: impl AsRef<Path>) -> Result<()> {
 }
 
 /// Creates a write logger that writes to files in [dir].
-fn create_write_logger(dir: impl AsRef<Path>) -> Result<Box<WriteLogger<fs::File>>> {
-    let dir = dir.as_ref().join("log");
-    fs::create_dir_all(&dir)?;
-    let filename = dir.join(Local::now().format("archipelago-%Y-%m-%d.log").to_string());
+fn create_write_logger(dir: impl AsRef<Path>) -> Result<Box<WriteLogger<LogFileWriter>>> {
     Ok(WriteLogger::new(
         LevelFilter::Info,
         simplelog::Config::default(),
-        fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(filename)?,
+        log_file::open(dir.as_ref().join("log"))?,
     ))
 }
 
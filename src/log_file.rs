@@ -0,0 +1,88 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, sync::Mutex};
+
+use anyhow::Result;
+use chrono::prelude::*;
+
+/// The file that the write logger is currently writing to, or None if the
+/// write logger hasn't been initialized.
+static LOG_FILE: Mutex<Option<LogFile>> = Mutex::new(None);
+
+/// An open log file along with the root log directory it lives beneath.
+struct LogFile {
+    /// The root directory for all log files.
+    root: PathBuf,
+
+    /// The file that logs are currently being written to.
+    file: fs::File,
+}
+
+/// A [Write] implementation that writes to the current log file. Unlike a
+/// plain [fs::File], this can be redirected to a new file after the logger has
+/// been initialized using [switch_to_seed].
+pub struct LogFileWriter;
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(log) => log.file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(log) => log.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Opens a date-based log file in [root] and returns a writer for it.
+pub fn open(root: impl AsRef<Path>) -> Result<LogFileWriter> {
+    let root = root.as_ref().to_path_buf();
+    let file = open_dated_file(&root)?;
+    *LOG_FILE.lock().unwrap() = Some(LogFile { root, file });
+    Ok(LogFileWriter)
+}
+
+/// Returns the root directory that logs are being written to, or None if the
+/// log file hasn't been opened.
+pub fn root() -> Option<PathBuf> {
+    LOG_FILE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|log| log.root.clone())
+}
+
+/// Redirects all future logs to a date-based file in a subdirectory specific
+/// to [seed]. Returns the directory that logs are now written to.
+///
+/// This does nothing if the log file hasn't been opened yet.
+pub fn switch_to_seed(seed: &str) -> Result<Option<PathBuf>> {
+    let mut log_file = LOG_FILE.lock().unwrap();
+    let Some(log) = log_file.as_mut() else {
+        return Ok(None);
+    };
+
+    // Seed names are generally numeric, but sanitize them anyway just in case.
+    let seed = seed
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let dir = log.root.join(seed);
+    log.file = open_dated_file(&dir)?;
+    Ok(Some(dir))
+}
+
+/// Opens a log file for the current date in [dir], creating it if necessary.
+fn open_dated_file(dir: &Path) -> Result<fs::File> {
+    fs::create_dir_all(dir)?;
+    let filename = dir.join(Local::now().format("archipelago-%Y-%m-%d.log").to_string());
+    Ok(fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filename)?)
+}
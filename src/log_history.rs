@@ -0,0 +1,72 @@
+use std::io::{BufRead, BufReader, Write};
+use std::{collections::VecDeque, fs, path::PathBuf};
+
+use anyhow::Result;
+use archipelago_rs as ap;
+use chrono::prelude::*;
+use log::*;
+
+use crate::utils;
+
+/// A record of the messages displayed in the overlay, persisted to disk so that
+/// they can be shown again the next time the game starts.
+pub struct LogHistory {
+    /// The file that new messages are appended to.
+    file: fs::File,
+}
+
+/// A single message loaded from a previous session.
+pub struct HistoryEntry {
+    /// The local time at which the message was logged, already formatted for
+    /// display.
+    pub time: String,
+
+    /// The plain text of the message.
+    pub text: String,
+}
+
+impl LogHistory {
+    /// Loads the most recent [limit] messages from the history file and opens
+    /// it for appending new messages.
+    ///
+    /// This also trims the history file to [limit] messages so it doesn't grow
+    /// without bound.
+    pub fn load(limit: usize) -> Result<(Self, Vec<HistoryEntry>)> {
+        let path = Self::path()?;
+        let mut entries = VecDeque::with_capacity(limit);
+        if let Ok(file) = fs::File::open(&path) {
+            for line in BufReader::new(file).lines() {
+                let Ok((time, text)) = json::from_str::<(String, String)>(&line?) else {
+                    continue;
+                };
+                if entries.len() >= limit {
+                    entries.pop_front();
+                }
+                entries.push_back(HistoryEntry { time, text });
+            }
+        }
+
+        let mut file = fs::File::create(&path)?;
+        for entry in &entries {
+            writeln!(file, "{}", json::to_string(&(&entry.time, &entry.text))?)?;
+        }
+
+        Ok((Self { file }, entries.into()))
+    }
+
+    /// Appends [print] to the history file.
+    pub fn append(&mut self, print: &ap::Print) {
+        let time = Local::now().format("%Y-%m-%d %H:%M").to_string();
+        let result = json::to_string(&(time, print.to_string()))
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(writeln!(self.file, "{}", line)?));
+        if let Err(err) = result {
+            warn!("Failed to write overlay log history: {err}");
+        }
+    }
+
+    /// The path to the history file.
+    fn path() -> Result<PathBuf> {
+        Ok(utils::mod_directory()?.join("overlay-log.jsonl"))
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::{Duration, Instant};
 use std::{mem, ptr, str::FromStr};
 
 use archipelago_rs::{self as ap, RichText, TextColor};
@@ -9,11 +10,17 @@ use imgui_sys::igSetWindowFocus_Str;
 use log::*;
 use regex_macro::regex;
 
-use crate::core::Core;
+use crate::config;
+use crate::core::{Core, ItemClass};
+use crate::utils;
 
+mod settings;
 mod text_input_history;
+mod toasts;
 
+use settings::{HOTKEYS, HotkeyAction, MAX_FONT_SCALE, MIN_FONT_SCALE, OverlaySettings};
 use text_input_history::TextInputHistory;
+use toasts::Toasts;
 
 const GREEN: ImColor32 = ImColor32::from_rgb(0x8A, 0xE2, 0x43);
 const RED: ImColor32 = ImColor32::from_rgb(0xFF, 0x44, 0x44);
@@ -26,6 +33,38 @@ const BLUE: ImColor32 = ImColor32::from_rgb(0x82, 0xA9, 0xD4);
 const MAGENTA: ImColor32 = ImColor32::from_rgb(0xBF, 0x9B, 0xBC);
 const CYAN: ImColor32 = ImColor32::from_rgb(0x34, 0xE2, 0xE2);
 
+/// How long location checks can go unacknowledged by the server before we
+/// highlight them as a potential problem.
+const UNACKNOWLEDGED_WARNING_TIME: Duration = Duration::from_secs(5);
+
+/// Archipelago server commands that can be tab-completed in the say input.
+const COMPLETABLE_COMMANDS: &[&str] = &[
+    "!alias",
+    "!checked",
+    "!collect",
+    "!countdown",
+    "!getitem",
+    "!help",
+    "!hint",
+    "!hint_location",
+    "!missing",
+    "!players",
+    "!release",
+    "!remaining",
+    "!status",
+];
+
+/// The check confirmation time above which the connection is highlighted as
+/// slow.
+const SLOW_CHECK_CONFIRMATION: Duration = Duration::from_millis(300);
+
+/// The check confirmation time above which the connection is highlighted as
+/// very slow.
+const VERY_SLOW_CHECK_CONFIRMATION: Duration = Duration::from_secs(1);
+
+/// The number of received items to show in the recent items ribbon.
+const RECENT_ITEMS_COUNT: usize = 5;
+
 /// The visual overlay that appears on top of the game.
 #[derive(Default)]
 pub struct Overlay {
@@ -36,15 +75,40 @@ pub struct Overlay {
     /// The URL field in the modal connection popup.
     popup_url: String,
 
+    /// The slot name field in the modal connection popup.
+    popup_slot: String,
+
+    /// The password field in the modal connection popup.
+    popup_password: String,
+
+    /// The seed field in the modal connection popup. This is only shown when
+    /// the player is setting up a new `apconfig.json`.
+    popup_seed: String,
+
+    /// The error from validating [popup_url], if the player tried to connect
+    /// with an invalid URL. Cleared when they edit the URL.
+    popup_url_error: Option<String>,
+
     /// The text the user typed in the say input.
     say_input: String,
 
     /// The history of messages sent to the say input.
     say_history: TextInputHistory,
 
+    /// The text typed in the log filter. Only log messages that contain this
+    /// text (case-insensitively) are displayed.
+    log_filter: String,
+
     /// Whether the log was previously scrolled all the way down.
     log_was_scrolled_down: bool,
 
+    /// The log's scroll position when a message that isn't relevant to the
+    /// player arrived while the log was scrolled down, if
+    /// [OverlaySettings::auto_scroll_only_mine] is enabled. As long as the
+    /// player doesn't scroll away from this position, the log is still treated
+    /// as scrolled down so that the next relevant message scrolls it.
+    held_scroll_y: Option<f32>,
+
     /// The number of logs that were most recently emitted. This is used to
     /// determine when new logs are emitted for [frames_since_new_logs].
     logs_emitted: usize,
@@ -53,15 +117,22 @@ pub struct Overlay {
     /// We use this to determine when to auto-scroll the log window.
     frames_since_new_logs: u64,
 
-    /// The current font scale for the overlay UI.
-    font_scale: f32,
-
-    /// The unfocused window opacity for the overlay UI.
-    unfocused_window_opacity: f32,
-
     /// Whether the settings window is currently visible.
     settings_window_visible: bool,
 
+    /// The time at which the server first had unacknowledged location checks,
+    /// or None if all checks have been acknowledged.
+    unacknowledged_since: Option<Instant>,
+
+    /// Whether the hints window is currently visible.
+    hints_window_visible: bool,
+
+    /// The settings that persist between sessions.
+    settings: OverlaySettings,
+
+    /// Notifications for items the player recently received.
+    toasts: Toasts,
+
     /// Whether the game was on the main menu in the previous frame.
     was_main_menu: bool,
 
@@ -78,6 +149,28 @@ pub struct Overlay {
     /// The size of the main overlay window in the previous frame. Used to
     /// resize when entering and exiting compact mode.
     previous_size: Option<[f32; 2]>,
+
+    /// Whether the player asked to move the main window back to its default
+    /// position and size. This is applied on the next frame.
+    reset_window_layout: bool,
+
+    /// Whether the player has hidden the overlay entirely using
+    /// [HotkeyAction::ToggleOverlay].
+    hidden: bool,
+
+    /// Whether the player opened the chat input with
+    /// [HotkeyAction::FocusChat]. While this is set, the chat input is shown
+    /// even in compact mode. It's closed again once the player sends a message
+    /// or presses Escape.
+    chat_open: bool,
+
+    /// The action the player is choosing a new hotkey for in the settings
+    /// window, if any.
+    rebinding_hotkey: Option<HotkeyAction>,
+
+    /// The reason the player's most recent attempt to rebind a hotkey failed,
+    /// if it did.
+    hotkey_error: Option<String>,
 }
 
 // Safety: The sole Overlay instance is owned by Hudhook, which only ever
@@ -90,8 +183,11 @@ impl Overlay {
     /// Creates a new instance of the overlay and the core mod logic.
     pub fn new() -> Self {
         Self {
-            font_scale: 1.8,
-            unfocused_window_opacity: 0.4,
+            settings: OverlaySettings::load(),
+            say_history: match utils::mod_directory() {
+                Ok(dir) => TextInputHistory::persistent(dir.join("chat-history.json")),
+                Err(_) => TextInputHistory::new(),
+            },
             was_compact_mode: true,
             ..Default::default()
         }
@@ -102,8 +198,95 @@ impl Overlay {
     /// We don't store `core` directly in the overlay so that we can ensure that
     /// its mutex is only locked once per render.
     pub fn render(&mut self, ui: &mut Ui, core: &mut Core) {
-        self.render_main_window(ui, core);
-        self.render_settings_window(ui);
+        if let Some(action) = self.rebinding_hotkey {
+            self.capture_hotkey(ui, action);
+        } else {
+            for action in HotkeyAction::ALL {
+                if self
+                    .settings
+                    .key(action)
+                    .is_some_and(|key| ui.is_key_pressed_no_repeat(key))
+                {
+                    self.run_hotkey(action, core);
+                }
+            }
+        }
+
+        if self.hidden {
+            // Drop granted items so their toasts don't all appear at once
+            // when the overlay is shown again.
+            core.drain_granted_items().for_each(drop);
+            return;
+        }
+
+        if self.settings.status_line_only {
+            self.render_status_line_window(ui, core);
+        } else {
+            self.render_main_window(ui, core);
+        }
+        self.render_settings_window(ui, core);
+        self.render_hints_window(ui, core);
+        self.render_toasts(ui, core);
+    }
+
+    /// Performs the action for a hotkey the player pressed.
+    fn run_hotkey(&mut self, action: HotkeyAction, core: &mut Core) {
+        match action {
+            HotkeyAction::ToggleOverlay => self.hidden = !self.hidden,
+            HotkeyAction::FocusChat => {
+                self.hidden = false;
+                self.chat_open = true;
+                self.focus_say_input_next_frame = true;
+            }
+            HotkeyAction::ClearLog => core.clear_log(),
+            HotkeyAction::PauseDeathLink => core.toggle_death_link_paused(),
+        }
+    }
+
+    /// Waits for the player to press a key to bind to [action]. Escape
+    /// cancels, and Backspace or Delete removes the existing binding.
+    fn capture_hotkey(&mut self, ui: &Ui, action: HotkeyAction) {
+        let name = if ui.is_key_pressed(Key::Escape) {
+            self.rebinding_hotkey = None;
+            return;
+        } else if ui.is_key_pressed(Key::Backspace) || ui.is_key_pressed(Key::Delete) {
+            None
+        } else if let Some((name, _)) = HOTKEYS
+            .iter()
+            .find(|(_, key)| ui.is_key_pressed_no_repeat(*key))
+        {
+            Some(*name)
+        } else {
+            return;
+        };
+
+        self.rebinding_hotkey = None;
+        match self.settings.set_hotkey(action, name) {
+            Ok(()) => self.settings.save(),
+            Err(err) => self.hotkey_error = Some(err.to_string()),
+        }
+    }
+
+    /// Renders notifications for items the player just received just below
+    /// the main overlay window.
+    fn render_toasts(&mut self, ui: &Ui, core: &mut Core) {
+        // Always drain the items so they don't pile up while toasts are off.
+        for item in core.drain_granted_items() {
+            if self.settings.show_item_toasts {
+                self.toasts.push(item);
+            }
+        }
+
+        let Some(viewport_size) = self.viewport_size else {
+            return;
+        };
+        let overlay_height = if self.settings.status_line_only {
+            ui.frame_height_with_spacing() * 2.
+        } else {
+            self.previous_size.map_or(0., |size| size[1])
+        };
+        self.toasts
+            .render(ui, [viewport_size[0] - 30., 30. + overlay_height + 10.]);
     }
 
     /// See [ImguiRenderLoop::before_render], but takes a reference to [Core] as
@@ -121,7 +304,24 @@ impl Overlay {
 
         // Set the font scale here because we need the frame height later to
         // calculate the main window size, which depends on it.
-        ctx.io_mut().font_global_scale = self.font_scale;
+        ctx.io_mut().font_global_scale = self.settings.font_scale;
+
+        // If the overlay doesn't capture the mouse, it shouldn't react to it
+        // either, or clicks would both press buttons and reach the game. The
+        // overlay is still usable with the keyboard, including to turn this
+        // setting back on.
+        let config_flags = &mut ctx.io_mut().config_flags;
+        config_flags.set(ConfigFlags::NO_MOUSE, !self.settings.captures_mouse);
+        config_flags.set(
+            ConfigFlags::NAV_ENABLE_KEYBOARD,
+            !self.settings.captures_mouse,
+        );
+    }
+
+    /// Returns whether mouse input to the game should be blocked while the
+    /// overlay wants to use the mouse.
+    pub fn captures_mouse(&self) -> bool {
+        self.settings.captures_mouse
     }
 
     /// Render the primary overlay window and any popups it opens.
@@ -142,12 +342,19 @@ impl Overlay {
             (self.was_main_menu && !self.is_main_menu())
         {
             unsafe { igSetWindowFocus_Str(ptr::null()) };
+            self.chat_open = false;
+        }
+
+        // Like most multiplayer games, let the player press enter to start
+        // typing a message once the overlay has focus.
+        if self.was_window_focused && !ui.is_any_item_active() && ui.is_key_pressed(Key::Enter) {
+            self.focus_say_input_next_frame = true;
         }
 
         let window_opacity = if self.was_window_focused {
             1.0
         } else {
-            self.unfocused_window_opacity
+            self.settings.unfocused_window_opacity
         };
         let mut bg_color = [0.0, 0.0, 0.0, window_opacity];
         let _bg = ui.push_style_color(StyleColor::WindowBg, bg_color);
@@ -156,19 +363,40 @@ impl Overlay {
         let _popup_bg = ui.push_style_color(StyleColor::PopupBg, bg_color);
 
         let mut builder = ui
+            // The visible title changes with the connection state, so use a
+            // stable ID to keep ImGui's window state (like its position).
             .window(format!(
-                "Archipelago Client {} [{}]###ap-client-overlay",
+                "Archipelago Client {} [{}]{}###ap-client-overlay",
                 env!("CARGO_PKG_VERSION"),
-                match core.connection_state_type() {
-                    ap::ConnectionStateType::Connected => "Connected",
-                    ap::ConnectionStateType::Connecting => "Connecting...",
-                    ap::ConnectionStateType::Disconnected => "Disconnected",
+                connection_status(core),
+                // Include the slot name to distinguish multiple game
+                // instances.
+                match core.client() {
+                    Some(client) => format!(" - {}", client.this_player().name()),
+                    None => String::new(),
                 }
             ))
-            .position([viewport_size[0] - 30., 30.], Condition::FirstUseEver)
-            .position_pivot([1., 0.])
             .menu_bar(true);
 
+        // Restore the window's saved position and size once per session, since
+        // imgui doesn't persist them itself.
+        let reset_window_layout = mem::take(&mut self.reset_window_layout);
+        builder = match self.settings.window_position {
+            Some(position) if !reset_window_layout => builder
+                .position(position, Condition::Once)
+                .position_pivot([0., 0.]),
+            _ => builder
+                .position(
+                    [viewport_size[0] - 30., 30.],
+                    if reset_window_layout {
+                        Condition::Always
+                    } else {
+                        Condition::FirstUseEver
+                    },
+                )
+                .position_pivot([1., 0.]),
+        };
+
         // When the menu opens or closes, add or remove space from the bottom of
         // the overlay for the message bar and horizontal scrollbar.
         let is_compact_mode = self.is_compact_mode(core);
@@ -193,20 +421,37 @@ impl Overlay {
                     Condition::Always,
                 )
             }
-            _ => builder.size([viewport_size[0] * 0.4, 300.], Condition::FirstUseEver),
+            _ if reset_window_layout => {
+                builder.size([viewport_size[0] * 0.4, 300.], Condition::Always)
+            }
+            _ => match self.settings.window_size {
+                Some(size) => builder.size(size, Condition::Once),
+                None => builder.size([viewport_size[0] * 0.4, 300.], Condition::FirstUseEver),
+            },
         };
 
         let focus_say_input = mem::take(&mut self.focus_say_input_next_frame);
         let collapsed = builder
             .build(|| {
-                self.render_menu_bar(ui);
+                self.render_menu_bar(ui, core);
                 ui.separator();
+                self.render_recent_items(ui, core);
+                self.render_check_progress(ui, core);
+                self.render_warnings(ui, core);
+                self.render_grace_period(ui, core);
+                self.render_item_backlog(ui, core);
                 self.render_log_window(ui, core);
                 if !is_compact_mode {
-                    if core.is_disconnected() {
-                        self.render_connection_buttons(ui, core);
-                    } else {
-                        self.render_say_input(ui, core, focus_say_input);
+                    match core.connection_state_type() {
+                        ap::ConnectionStateType::Disconnected => {
+                            self.render_connection_buttons(ui, core)
+                        }
+                        ap::ConnectionStateType::Connecting => {
+                            self.render_connecting_banner(ui, core)
+                        }
+                        ap::ConnectionStateType::Connected => {
+                            self.render_say_input(ui, core, focus_say_input)
+                        }
                     }
                 }
                 self.render_url_modal_popup(ui, core);
@@ -214,6 +459,24 @@ impl Overlay {
                 self.was_window_focused =
                     ui.is_window_focused_with_flags(WindowFocusedFlags::ROOT_AND_CHILD_WINDOWS);
                 self.previous_size = Some(ui.window_size());
+
+                // Wait until the player lets go of the mouse to avoid saving
+                // every frame while the window is dragged.
+                if !ui.is_mouse_down(MouseButton::Left) {
+                    let position = Some(ui.window_pos());
+                    let size = if is_compact_mode {
+                        self.settings.window_size
+                    } else {
+                        Some(ui.window_size())
+                    };
+                    if position != self.settings.window_position
+                        || size != self.settings.window_size
+                    {
+                        self.settings.window_position = position;
+                        self.settings.window_size = size;
+                        self.settings.save();
+                    }
+                }
             })
             .is_none();
 
@@ -225,6 +488,43 @@ impl Overlay {
         }
     }
 
+    /// Renders a minimal window which shows only a single line of status
+    /// information, in place of the main window.
+    fn render_status_line_window(&mut self, ui: &Ui, core: &mut Core) {
+        let Some(viewport_size) = self.viewport_size else {
+            return;
+        };
+
+        let _bg = ui.push_style_color(
+            StyleColor::WindowBg,
+            [0.0, 0.0, 0.0, self.settings.unfocused_window_opacity],
+        );
+        ui.window("###ap-client-status-line")
+            .position([viewport_size[0] - 30., 30.], Condition::FirstUseEver)
+            .position_pivot([1., 0.])
+            .title_bar(false)
+            .always_auto_resize(true)
+            .build(|| {
+                let mut status = format!("AP: {}", connection_status(core));
+                if let Some(pending) = core.pending_item_count() {
+                    status.push_str(&format!(" | Items queued: {}", pending));
+                }
+                if let Some((checked, total)) = core.location_progress() {
+                    status.push_str(&format!(" | Checks: {}/{}", checked, total));
+                } else if let Some(checked) = core.checked_location_count() {
+                    status.push_str(&format!(" | Checks: {}", checked));
+                }
+
+                ui.align_text_to_frame_padding();
+                ui.text(status);
+                ui.same_line();
+                if ui.small_button("Expand") {
+                    self.settings.status_line_only = false;
+                    self.settings.save();
+                }
+            });
+    }
+
     /// Renders the modal popup which queries the player for connection
     /// information.
     fn render_url_modal_popup(&mut self, ui: &Ui, core: &mut Core) {
@@ -235,37 +535,165 @@ impl Overlay {
             .always_auto_resize(true)
             .build(|| {
                 {
-                    let _item_width = ui.push_item_width(500. * self.font_scale);
-                    ui.input_text("Room URL", &mut self.popup_url)
+                    let _item_width = ui.push_item_width(500. * self.settings.font_scale);
+                    if ui
+                        .input_text("Room URL", &mut self.popup_url)
                         .hint("archipelago.gg:12345")
                         .chars_noblank(true)
+                        .build()
+                    {
+                        self.popup_url_error = None;
+                    }
+                    if let Some(err) = &self.popup_url_error {
+                        ui.text_colored(RED.to_rgba_f32s(), err);
+                    }
+                    // The slot is baked into the game files by
+                    // DS3Randomizer.exe, so it can only be chosen during setup.
+                    ui.input_text("Slot", &mut self.popup_slot)
+                        .read_only(!core.needs_setup())
+                        .build();
+                    ui.input_text("Password", &mut self.popup_password)
+                        .password(true)
                         .build();
+                    if core.needs_setup() {
+                        ui.input_text("Seed", &mut self.popup_seed)
+                            .chars_noblank(true)
+                            .build();
+                    }
                 }
 
-                ui.disabled(self.popup_url.is_empty(), || {
-                    if ui.button("Connect") {
-                        ui.close_current_popup();
-                        if let Err(e) = core.update_url(&self.popup_url) {
-                            error!("Failed to save config: {e}");
+                ui.disabled(
+                    self.popup_url.is_empty()
+                        || self.popup_slot.is_empty()
+                        || (core.needs_setup() && self.popup_seed.is_empty()),
+                    || {
+                        if ui.button("Connect") {
+                            match config::validate_url(&self.popup_url) {
+                                Ok(url) => {
+                                    ui.close_current_popup();
+                                    let result = if core.needs_setup() {
+                                        core.set_up_connection(
+                                            url,
+                                            &self.popup_slot,
+                                            &self.popup_seed,
+                                            Some(&self.popup_password),
+                                        )
+                                    } else {
+                                        core.update_connection(url, Some(&self.popup_password))
+                                    };
+                                    if let Err(e) = result {
+                                        error!("Failed to save config: {e}");
+                                    }
+                                }
+                                Err(err) => self.popup_url_error = Some(err.to_string()),
+                            }
                         }
-                    }
-                });
+                    },
+                );
             });
     }
 
     /// Renders the menu bar.
-    fn render_menu_bar(&mut self, ui: &Ui) {
+    fn render_menu_bar(&mut self, ui: &Ui, core: &mut Core) {
         ui.menu_bar(|| {
             if ui.menu_item("Settings") {
                 log::warn!("Click registered");
                 self.settings_window_visible = true;
             }
+
+            if ui.menu_item("Hints") {
+                self.hints_window_visible = true;
+            }
+
+            ui.menu("Tools", || {
+                if ui
+                    .menu_item_config("Export Checked Locations")
+                    .enabled(core.is_in_game())
+                    .build()
+                {
+                    match core.export_checked_locations() {
+                        Ok(path) => core.log(format!(
+                            "Exported checked locations to {}",
+                            path.to_string_lossy()
+                        )),
+                        Err(err) => core.log(RichText::Color {
+                            text: format!("Failed to export checked locations: {err}"),
+                            color: TextColor::Red,
+                        }),
+                    }
+                }
+            });
+
+            if let Some(goal) = core.goal_description() {
+                let goal = goal.to_string();
+                ui.menu("Goal", || {
+                    ui.text(goal);
+                    if core.has_sent_goal() {
+                        ui.text_colored(GREEN.to_rgba_f32s(), "Complete!");
+                    }
+                });
+            }
+
+            if core.is_death_link_paused() {
+                ui.text_colored(YELLOW.to_rgba_f32s(), "DeathLink paused");
+            } else if let Some(remaining) = core.remaining_death_link_grace_period() {
+                // Let the player know why a death didn't go through.
+                ui.text_colored(
+                    BLACK.to_rgba_f32s(),
+                    format!("DeathLink resumes in {}s", remaining.as_secs_f32().ceil()),
+                );
+            }
+
+            match core.unacknowledged_location_count() {
+                Some(count) if count > 0 => {
+                    let since = *self.unacknowledged_since.get_or_insert_with(Instant::now);
+                    // Checks normally take well under a second to acknowledge,
+                    // so if they take longer there's probably a connectivity
+                    // problem.
+                    let color = if since.elapsed() > UNACKNOWLEDGED_WARNING_TIME {
+                        RED
+                    } else {
+                        YELLOW
+                    };
+                    ui.text_colored(color.to_rgba_f32s(), format!("Checks pending: {}", count));
+                }
+                _ => self.unacknowledged_since = None,
+            }
+
+            match core.online_players() {
+                Some((Some(online), total)) => ui.text_colored(
+                    (if online >= total { GREEN } else { YELLOW }).to_rgba_f32s(),
+                    format!("Players online: {}/{}", online, total),
+                ),
+                Some((None, total)) => ui.text_colored(
+                    BLACK.to_rgba_f32s(),
+                    format!("Players online: unknown/{}", total),
+                ),
+                None => {}
+            }
+
+            if let Some(time) = core.check_confirmation_time() {
+                let color = if time > VERY_SLOW_CHECK_CONFIRMATION {
+                    RED
+                } else if time > SLOW_CHECK_CONFIRMATION {
+                    YELLOW
+                } else {
+                    BLACK
+                };
+                ui.text_colored(
+                    color.to_rgba_f32s(),
+                    format!("Checks confirmed in: {}ms", time.as_millis()),
+                );
+            }
         });
     }
 
     /// Renders the settings popup.
-    fn render_settings_window(&mut self, ui: &Ui) {
+    fn render_settings_window(&mut self, ui: &Ui, core: &mut Core) {
         if !self.settings_window_visible {
+            // Otherwise the next function key pressed would silently rebind
+            // the hotkey rather than triggering its action.
+            self.rebinding_hotkey = None;
             return;
         }
 
@@ -280,21 +708,174 @@ impl Overlay {
                 ui.text("Font Size ");
                 ui.same_line();
                 if ui.button("-##font-size-decrease-button") {
-                    self.font_scale = (self.font_scale - 0.1).max(0.5);
+                    self.settings.font_scale = (self.settings.font_scale - 0.1).max(MIN_FONT_SCALE);
+                    self.settings.save();
                 }
                 ui.same_line();
                 if ui.button("+##font-size-increase-button") {
-                    self.font_scale = (self.font_scale + 0.1).min(4.0);
+                    self.settings.font_scale = (self.settings.font_scale + 0.1).min(MAX_FONT_SCALE);
+                    self.settings.save();
                 }
 
-                let mut opacity_percent = (self.unfocused_window_opacity * 100.0).round() as i32;
-                let _slider_width = ui.push_item_width(150. * self.font_scale);
+                let mut opacity_percent =
+                    (self.settings.unfocused_window_opacity * 100.0).round() as i32;
+                let _slider_width = ui.push_item_width(150. * self.settings.font_scale);
                 ui.text("Unfocused Opacity ");
                 ui.same_line();
-                ui.slider_config("##unfocused-opacity-slider", 0, 100)
+                if ui
+                    .slider_config("##unfocused-opacity-slider", 0, 100)
                     .display_format("%d%%")
-                    .build(&mut opacity_percent);
-                self.unfocused_window_opacity = (opacity_percent as f32) / 100.0;
+                    .build(&mut opacity_percent)
+                {
+                    self.settings.unfocused_window_opacity = (opacity_percent as f32) / 100.0;
+                }
+                // Only write the settings once the player lets go of the
+                // slider, rather than on every frame while they're dragging it.
+                if ui.is_item_deactivated_after_edit() {
+                    self.settings.save();
+                }
+
+                let mut grace_period = core.config().death_link_grace_period().as_secs() as u32;
+                ui.text("DeathLink Grace Period ");
+                ui.same_line();
+                if ui
+                    .slider_config("##death-link-grace-period-slider", 5, 120)
+                    .display_format("%ds")
+                    .build(&mut grace_period)
+                {
+                    core.set_death_link_grace_period(Duration::from_secs(grace_period.into()));
+                }
+                // Only write the config once the player lets go of the slider,
+                // rather than on every frame while they're dragging it.
+                if ui.is_item_deactivated_after_edit()
+                    && let Err(err) = core.save_config()
+                {
+                    core.log(RichText::Color {
+                        text: format!("Failed to save config: {err}"),
+                        color: TextColor::Red,
+                    });
+                }
+
+                let mut send_death_links = core.config().send_death_links();
+                let mut receive_death_links = core.config().receive_death_links();
+                let send_changed = ui.checkbox("Send DeathLinks", &mut send_death_links);
+                let receive_changed = ui.checkbox("Receive DeathLinks", &mut receive_death_links);
+                let result = if send_changed {
+                    core.set_send_death_links(send_death_links)
+                } else if receive_changed {
+                    core.set_receive_death_links(receive_death_links)
+                } else {
+                    Ok(())
+                };
+                if let Err(err) = result {
+                    core.log(RichText::Color {
+                        text: format!("Failed to save config: {err}"),
+                        color: TextColor::Red,
+                    });
+                }
+
+                let mut item_sound_cues = core.config().item_sound_cues();
+                if ui.checkbox("Item Sound Cues", &mut item_sound_cues)
+                    && let Err(err) = core.set_item_sound_cues(item_sound_cues)
+                {
+                    core.log(RichText::Color {
+                        text: format!("Failed to save config: {err}"),
+                        color: TextColor::Red,
+                    });
+                }
+
+                if ui.checkbox("Status Line Only", &mut self.settings.status_line_only) {
+                    self.settings.save();
+                }
+
+                if ui.checkbox("Overlay Captures Mouse", &mut self.settings.captures_mouse) {
+                    self.settings.save();
+                }
+
+                if ui.checkbox("Show Item and Location IDs", &mut self.settings.show_ids) {
+                    self.settings.save();
+                }
+
+                if ui.checkbox(
+                    "Auto-Scroll Only for My Events",
+                    &mut self.settings.auto_scroll_only_mine,
+                ) {
+                    self.settings.save();
+                }
+
+                if ui.checkbox(
+                    "Show Check Progress",
+                    &mut self.settings.show_check_progress,
+                ) {
+                    self.settings.save();
+                }
+
+                if ui.checkbox(
+                    "Show Recently Received Items",
+                    &mut self.settings.show_recent_items,
+                ) {
+                    self.settings.save();
+                }
+
+                if ui.checkbox(
+                    "Show Message Background Colors",
+                    &mut self.settings.show_background_colors,
+                ) {
+                    self.settings.save();
+                }
+
+                if ui.checkbox(
+                    "Show Item Notifications",
+                    &mut self.settings.show_item_toasts,
+                ) {
+                    self.settings.save();
+                }
+
+                ui.separator_with_text("Show in Log");
+                let mut filters_changed = false;
+                for (label, value) in [
+                    ("Chat", &mut self.settings.show_chat),
+                    ("Item Sends", &mut self.settings.show_item_sends),
+                    ("Hints", &mut self.settings.show_hints),
+                    ("Command Results", &mut self.settings.show_command_results),
+                    ("Server Messages", &mut self.settings.show_server_messages),
+                ] {
+                    filters_changed |= ui.checkbox(label, value);
+                }
+                if filters_changed {
+                    self.settings.save();
+                }
+                ui.separator();
+
+                ui.separator_with_text("Hotkeys");
+                for action in HotkeyAction::ALL {
+                    let binding = if self.rebinding_hotkey == Some(action) {
+                        "Press a key..."
+                    } else {
+                        self.settings.hotkey(action).unwrap_or("None")
+                    };
+                    if ui.button(format!("{binding}##hotkey-{}", action.label())) {
+                        self.rebinding_hotkey = Some(action);
+                        self.hotkey_error = None;
+                    }
+                    ui.same_line();
+                    ui.text(action.label());
+                }
+                if self.rebinding_hotkey.is_some() {
+                    ui.text_colored(
+                        BLACK.to_rgba_f32s(),
+                        "Press F1-F12, Backspace to unbind, or Escape to cancel.",
+                    );
+                }
+                if let Some(err) = &self.hotkey_error {
+                    ui.text_colored(RED.to_rgba_f32s(), err);
+                }
+                ui.separator();
+
+                if ui.button("Reset Window Position") {
+                    self.reset_window_layout = true;
+                }
+                ui.separator();
 
                 if ui.button("Ok") {
                     self.settings_window_visible = false;
@@ -302,10 +883,91 @@ impl Overlay {
             });
     }
 
+    /// Renders the window listing hints that involve this player.
+    ///
+    /// Hints for entrance-randomized seeds are listed separately, since the
+    /// entrance is often more useful than the location itself. Seeds without
+    /// entrance randomization just show a single list.
+    fn render_hints_window(&mut self, ui: &Ui, core: &Core) {
+        if !self.hints_window_visible {
+            return;
+        }
+
+        let (entrance_hints, other_hints): (Vec<_>, Vec<_>) =
+            core.hints().iter().partition(|message| {
+                message
+                    .data()
+                    .iter()
+                    .any(|part| matches!(part, RichText::EntranceName { .. }))
+            });
+
+        let show_ids = self.settings.show_ids;
+        let backgrounds = self.settings.show_background_colors;
+        let _bg = ui.push_style_color(StyleColor::WindowBg, [0.0, 0.0, 0.0, 1.0]);
+        ui.window("Archipelago Hints")
+            .size([800., 400.], Condition::FirstUseEver)
+            .collapsible(false)
+            .opened(&mut self.hints_window_visible)
+            .build(|| {
+                if entrance_hints.is_empty() && other_hints.is_empty() {
+                    ui.text_colored(
+                        BLACK.to_rgba_f32s(),
+                        "No hints involving you have been received this session.",
+                    );
+                    return;
+                }
+
+                if !entrance_hints.is_empty() {
+                    ui.separator_with_text("Entrances");
+                    for hint in &entrance_hints {
+                        write_message_data(ui, hint, false, show_ids, backgrounds, 0xFF);
+                    }
+                    ui.separator_with_text("Locations");
+                }
+                for hint in &other_hints {
+                    write_message_data(ui, hint, false, show_ids, backgrounds, 0xFF);
+                }
+            });
+    }
+
     /// Renders the buttons that allow the player to reconnect to Archipelago.
     /// These take the place of the text box when the client is disconnected.
     fn render_connection_buttons(&mut self, ui: &Ui, core: &mut Core) {
-        if ui.button("Reconnect") {
+        if core.needs_setup() {
+            ui.align_text_to_frame_padding();
+            ui.text("No apconfig.json found");
+            ui.same_line();
+            if ui.button("Set Up") {
+                ui.open_popup("#url-modal-popup");
+                self.popup_url.clear();
+                self.popup_url_error = None;
+                self.popup_slot.clear();
+                self.popup_password.clear();
+                self.popup_seed.clear();
+            }
+            return;
+        }
+
+        if core.is_waiting_for_save() {
+            ui.align_text_to_frame_padding();
+            ui.text("Load a save to connect");
+            ui.same_line();
+            if ui.button("Connect Now") {
+                core.reconnect();
+            }
+        } else if let Some(remaining) = core.time_until_reconnect() {
+            // Let the player skip the rest of the backoff delay if they know
+            // the server is back.
+            ui.align_text_to_frame_padding();
+            ui.text(format!(
+                "Reconnecting in {}s",
+                remaining.as_secs_f32().ceil()
+            ));
+            ui.same_line();
+            if ui.button("Reconnect Now") {
+                core.reconnect();
+            }
+        } else if ui.button("Reconnect") {
             core.reconnect();
         }
 
@@ -313,6 +975,160 @@ impl Overlay {
         if ui.button("Change URL") {
             ui.open_popup("#url-modal-popup");
             core.config().url().clone_into(&mut self.popup_url);
+            self.popup_url_error = None;
+            core.config().slot().clone_into(&mut self.popup_slot);
+            core.config()
+                .password()
+                .unwrap_or_default()
+                .clone_into(&mut self.popup_password);
+        }
+    }
+
+    /// Renders warnings about non-fatal problems the player should fix before
+    /// they become fatal.
+    fn render_warnings(&mut self, ui: &Ui, core: &mut Core) {
+        if let Some(version) = core.mismatched_randomizer_version() {
+            let _color = ui.push_style_color(StyleColor::Text, RED.to_rgba_f32s());
+            ui.text_wrapped(format!(
+                "Your apconfig.json was generated using static randomizer v{}, but this client \
+                 is v{}. Re-run the static randomizer before loading a save.",
+                version,
+                env!("CARGO_PKG_VERSION"),
+            ));
+            ui.separator();
+        }
+
+        if let Some((expected, actual)) = core.mismatched_data_package() {
+            let _color = ui.push_style_color(StyleColor::Text, YELLOW.to_rgba_f32s());
+            ui.text_wrapped(format!(
+                "This server's Dark Souls III apworld doesn't match the one your game was \
+                 randomized with, so you may receive the wrong items. Make sure you ran the \
+                 static randomizer against this room. (Expected data package {}, server has \
+                 {}.)",
+                expected, actual,
+            ));
+            ui.separator();
+        }
+
+        if core.awaiting_save_association() {
+            {
+                let _color = ui.push_style_color(StyleColor::Text, YELLOW.to_rgba_f32s());
+                ui.text_wrapped(
+                    "This save doesn't have any Archipelago data, so it was probably created \
+                     before the mod was installed. No items will be sent or received until you \
+                     confirm that it belongs to this multiworld.",
+                );
+            }
+            if ui.button("Use This Save")
+                && let Err(err) = core.associate_save()
+            {
+                core.log(ap::RichText::Color {
+                    text: format!("Couldn't use this save: {err}"),
+                    color: ap::TextColor::Red,
+                });
+            }
+            ui.separator();
+        }
+    }
+
+    /// Renders a single line listing the last few items the player received,
+    /// newest first, colored by classification. Older items are faded out so
+    /// the newest ones stand out.
+    fn render_recent_items(&mut self, ui: &Ui, core: &Core) {
+        if !self.settings.show_recent_items {
+            return;
+        }
+        let Some(client) = core.client() else {
+            return;
+        };
+        let items = client.received_items();
+        if items.is_empty() {
+            return;
+        }
+
+        ui.text_colored(BLACK.to_rgba_f32s(), "Recent:");
+        for (age, item) in items.iter().rev().take(RECENT_ITEMS_COUNT).enumerate() {
+            if age > 0 {
+                ui.same_line_with_spacing(0., 0.);
+                ui.text_colored(BLACK.to_rgba_f32s(), ",");
+            }
+            ui.same_line();
+
+            let alpha = 255 - (age as u8) * 35;
+            let obscured = core.is_item_obscured(item.sender().name(), item.location().id());
+            let name = if obscured { "???" } else { item.item().name() };
+            let color = item_color(Some(ItemClass::of(item.item())), obscured);
+            ui.text_colored(color.with_alpha(alpha).to_rgba_f32s(), name);
+        }
+        ui.separator();
+    }
+
+    /// Renders a progress bar showing how many of the player's locations have
+    /// been checked.
+    fn render_check_progress(&mut self, ui: &Ui, core: &Core) {
+        if !self.settings.show_check_progress {
+            return;
+        }
+        let Some((checked, total)) = core.location_progress().filter(|(_, total)| *total > 0)
+        else {
+            return;
+        };
+
+        ui.progress_bar(checked as f32 / total as f32)
+            .overlay_text(format!("Checks: {}/{}", checked, total))
+            .size([-1., 0.])
+            .build();
+    }
+
+    /// Renders a countdown until items start being granted after the player
+    /// loads into a game, so the wait doesn't look like the mod is stuck.
+    fn render_grace_period(&mut self, ui: &Ui, core: &Core) {
+        let Some(remaining) = core.remaining_grace_period() else {
+            return;
+        };
+
+        ui.text_colored(
+            BLACK.to_rgba_f32s(),
+            format!("Items resume in {}s...", remaining.as_secs_f32().ceil()),
+        );
+        ui.separator();
+    }
+
+    /// Renders the number of items still waiting to be granted once the mod
+    /// has started granting them, so a long backlog doesn't look like items
+    /// have gone missing.
+    fn render_item_backlog(&mut self, ui: &Ui, core: &Core) {
+        if !core.is_in_game() {
+            return;
+        }
+        let Some(pending) = core.pending_item_count().filter(|pending| *pending > 0) else {
+            return;
+        };
+
+        ui.text_colored(
+            BLACK.to_rgba_f32s(),
+            format!("Receiving items... ({pending} left)"),
+        );
+        ui.separator();
+    }
+
+    /// Renders information about the in-progress connection along with a
+    /// button to cancel it. This takes the place of the text box while the
+    /// client is connecting.
+    fn render_connecting_banner(&mut self, ui: &Ui, core: &mut Core) {
+        let Some(duration) = core.connecting_duration() else {
+            return;
+        };
+
+        ui.align_text_to_frame_padding();
+        ui.text(format!(
+            "Connecting to {} ({}s)",
+            core.config().url(),
+            duration.as_secs()
+        ));
+        ui.same_line();
+        if ui.button("Cancel") {
+            core.cancel_connection();
         }
     }
 
@@ -336,6 +1152,18 @@ impl Overlay {
             0.0
         };
 
+        // The filter is hidden in compact mode, so don't let it hide messages
+        // either.
+        let filter = if is_compact_mode {
+            String::new()
+        } else {
+            ui.set_next_item_width(-1.);
+            ui.input_text("##log-filter", &mut self.log_filter)
+                .hint("Filter log...")
+                .build();
+            self.log_filter.to_lowercase()
+        };
+
         ui.child_window("#log")
             .size([0.0, -input_height.ceil()])
             .draw_background(false)
@@ -343,38 +1171,78 @@ impl Overlay {
             .always_horizontal_scrollbar(!is_compact_mode)
             .build(|| {
                 let logs = core.logs();
-                if logs.len() != self.logs_emitted {
-                    self.frames_since_new_logs = 0;
-                    self.logs_emitted = logs.len();
+                // Compare the total count rather than the buffer's length,
+                // since the latter stops changing once the buffer is full.
+                if core.log_count() != self.logs_emitted {
+                    // Several messages can arrive in a single frame, so check
+                    // all of them rather than just the last.
+                    let new_logs = core.log_count().saturating_sub(self.logs_emitted);
+                    self.logs_emitted = core.log_count();
+                    if !self.settings.auto_scroll_only_mine
+                        || core
+                            .logs()
+                            .skip(logs.len().saturating_sub(new_logs))
+                            .any(|m| is_relevant(core, m))
+                    {
+                        self.frames_since_new_logs = 0;
+                        self.held_scroll_y = None;
+                    } else if self.log_was_scrolled_down {
+                        self.held_scroll_y.get_or_insert(ui.scroll_y());
+                    }
                 }
 
                 for message in logs {
+                    if !self.settings.shows_in_log(message) {
+                        continue;
+                    }
+
                     use ap::Print::*;
+                    let obscure_items = match message {
+                        ItemSend { item, .. } | ItemCheat { item, .. }
+                            if core.config().slot() == item.receiver().name() =>
+                        {
+                            core.is_item_obscured(item.sender().name(), item.location().id())
+                        }
+                        _ => false,
+                    };
+                    if !filter.is_empty()
+                        && !message_text(message.data(), obscure_items)
+                            .to_lowercase()
+                            .contains(&filter)
+                    {
+                        continue;
+                    }
+
+                    // Mark the server's responses to the player's commands so
+                    // they stand out from chat.
+                    if matches!(message, CommandResult { .. } | AdminCommandResult { .. }) {
+                        ui.text_colored(CYAN.to_rgba_f32s(), ">");
+                        ui.same_line();
+                    }
                     write_message_data(
                         ui,
-                        message.data(),
+                        message,
+                        obscure_items,
+                        self.settings.show_ids,
+                        self.settings.show_background_colors,
                         // De-emphasize miscellaneous server prints.
-                        match message {
-                            Chat { .. }
-                            | ServerChat { .. }
-                            | Tutorial { .. }
-                            | CommandResult { .. }
-                            | AdminCommandResult { .. }
-                            | Unknown { .. } => 0xff,
-                            ItemSend { item, .. } | ItemCheat { item, .. } | Hint { item, .. }
-                                if core.config().slot() == item.receiver().name()
-                                    || core.config().slot() == item.sender().name() =>
-                            {
-                                0xFF
-                            }
-                            _ => 0xAA,
+                        if is_relevant(core, message) {
+                            0xFF
+                        } else {
+                            0xAA
                         },
                     );
                 }
+
+                // If the player scrolls manually, stop holding the log.
+                if self.held_scroll_y.is_some_and(|y| y != ui.scroll_y()) {
+                    self.held_scroll_y = None;
+                }
                 if self.log_was_scrolled_down && self.frames_since_new_logs < 10 {
                     ui.set_scroll_y(ui.scroll_max_y());
                 }
-                self.log_was_scrolled_down = ui.scroll_y() == ui.scroll_max_y();
+                self.log_was_scrolled_down =
+                    self.held_scroll_y.is_some() || ui.scroll_y() == ui.scroll_max_y();
             });
     }
 
@@ -383,40 +1251,84 @@ impl Overlay {
     /// If `focus` is true, this forces the input to be in focus.
     fn render_say_input(&mut self, ui: &Ui, core: &mut Core, focus: bool) {
         ui.disabled(core.client().is_none(), || {
-            let arrow_button_width = ui.frame_height(); // Arrow buttons are square buttons.
+            // Arrow buttons are square buttons. The frame height already
+            // reflects the font scale, but style sizes like the item spacing
+            // don't, so we scale the latter manually. Everything is rounded to
+            // whole pixels because ImGui snaps item positions to pixels, and
+            // fractional widths can otherwise add up to push the arrow button
+            // past the edge of the window at large font scales.
+            let arrow_button_width = ui.frame_height().ceil();
             let style = ui.clone_style();
-            let spacing = style.item_spacing[0] * self.font_scale * 0.7;
-
-            let input_width = ui.push_item_width(-(arrow_button_width + spacing));
+            let spacing = (style.item_spacing[0] * self.settings.font_scale * 0.7)
+                .round()
+                .max(1.0);
+
+            // Only show the character counter once the player gets close to
+            // the limit.
+            let max_length = core.config().max_say_length();
+            let length = self.say_input.chars().count();
+            let counter = (length * 4 >= max_length * 3).then(|| format!("{length}/{max_length}"));
+            let counter_width = counter
+                .as_ref()
+                .map_or(0.0, |c| ui.calc_text_size(c)[0].ceil() + spacing);
+
+            let input_width = ui.push_item_width(-(arrow_button_width + spacing + counter_width));
             if focus {
                 ui.set_keyboard_focus_here();
             }
             let mut send = ui
                 .input_text("##say-input", &mut self.say_input)
                 .enter_returns_true(true)
-                .callback(InputTextCallback::HISTORY, &mut self.say_history)
+                .callback(
+                    InputTextCallback::HISTORY | InputTextCallback::COMPLETION,
+                    SayInputCallback(&mut self.say_history),
+                )
                 .build();
             drop(input_width);
 
+            if let Some(counter) = counter {
+                ui.same_line_with_spacing(0.0, spacing);
+                ui.text_colored(
+                    (if length > max_length { RED } else { WHITE }).to_rgba_f32s(),
+                    counter,
+                );
+            }
+
             ui.same_line_with_spacing(0.0, spacing);
             send = ui.arrow_button("##say-button", Direction::Right) || send;
 
-            if send {
-                // We don't have a great way to surface these errors, and
-                // they're non-fatal, so just ignore them.
+            if send && length > max_length {
+                core.log(RichText::Color {
+                    text: format!(
+                        "Message not sent: it's {} characters long, but the limit is {}.",
+                        length, max_length
+                    ),
+                    color: ap::TextColor::Red,
+                });
+                self.focus_say_input_next_frame = true;
+            } else if send {
                 let line = mem::take(&mut self.say_input);
                 self.say_history.add(line.clone());
                 self.say(line, core);
-                self.focus_say_input_next_frame = true;
+                if self.chat_open {
+                    // Hand input back to the game once the message is sent.
+                    self.chat_open = false;
+                    unsafe { igSetWindowFocus_Str(ptr::null()) };
+                } else {
+                    self.focus_say_input_next_frame = true;
+                }
             }
         });
     }
 
     /// Handles a command from the player, falling back to sending it to the
     /// server.
+    ///
+    /// Client-only commands that share a name with an Archipelago server
+    /// command use `/` rather than `!` to avoid shadowing it.
     fn say(&mut self, message: String, core: &mut Core) {
-        let Some(captures) = regex!("^(![^ ]+)( +)?(.*)?$").captures(message.trim()) else {
-            let _ = core.client_mut().unwrap().say(message);
+        let Some(captures) = regex!("^([!/][^ ]+)( +)?(.*)?$").captures(message.trim()) else {
+            core.say(message);
             return;
         };
 
@@ -478,6 +1390,158 @@ impl Overlay {
                 ]);
             }
 
+            "/missing" => {
+                if core.client().is_none() {
+                    core.log(RichText::Color {
+                        text: "Not connected to Archipelago".into(),
+                        color: ap::TextColor::Red,
+                    });
+                    return;
+                }
+
+                let Some(missing) = core.missing_progression_items() else {
+                    core.log(RichText::Color {
+                        text: "This seed's slot data doesn't list progression items. It may \
+                               have been generated with an older apworld."
+                            .into(),
+                        color: ap::TextColor::Red,
+                    });
+                    return;
+                };
+
+                if missing.is_empty() {
+                    core.log("You've received all your progression items from other worlds.");
+                    return;
+                }
+
+                let mut message = vec![RichText::from("Missing progression items: ")];
+                for (i, (name, count)) in missing.into_iter().enumerate() {
+                    if i != 0 {
+                        message.push(", ".into());
+                    }
+                    message.push(RichText::Color {
+                        text: name,
+                        color: ap::TextColor::Magenta,
+                    });
+                    if count > 1 {
+                        message.push(format!(" x{}", count).into());
+                    }
+                }
+                core.log(message);
+            }
+
+            "/slotdata" => {
+                let Some(client) = core.client() else {
+                    core.log(RichText::Color {
+                        text: "Not connected to Archipelago".into(),
+                        color: ap::TextColor::Red,
+                    });
+                    return;
+                };
+
+                let slot_data = client.slot_data();
+                info!("Slot data: {:#?}", slot_data);
+                let message = format!(
+                    "Goal flags: {:?}\n\
+                     Item ID mappings: {}\n\
+                     Item counts: {}\n\
+                     Progression items: {}\n\
+                     Options: {:?}",
+                    slot_data.goal,
+                    slot_data.ap_ids_to_item_ids.len(),
+                    slot_data.item_counts.len(),
+                    slot_data
+                        .progression_items
+                        .as_ref()
+                        .map_or("not provided".to_string(), |items| items.len().to_string()),
+                    slot_data.options,
+                );
+                core.log(message);
+            }
+
+            #[cfg(debug_assertions)]
+            "/regrant" => {
+                let Some(id) = arg().and_then(|id| i64::from_str(id).ok()) else {
+                    arg_error("/regrant AP_ITEM_ID");
+                    return;
+                };
+
+                match core.regrant_item(id) {
+                    Ok(name) => core.log(vec![
+                        RichText::Color {
+                            text: "Regranted ".into(),
+                            color: ap::TextColor::Yellow,
+                        },
+                        RichText::Color {
+                            text: name,
+                            color: ap::TextColor::Magenta,
+                        },
+                        format!(" (AP ID {})", id).into(),
+                    ]),
+                    Err(err) => core.log(RichText::Color {
+                        text: format!("Failed to regrant item: {}", err),
+                        color: ap::TextColor::Red,
+                    }),
+                }
+            }
+
+            #[cfg(debug_assertions)]
+            "/deliverupto" => {
+                let ceiling = match arg() {
+                    Some("all") => None,
+                    Some(index) => match usize::from_str(index) {
+                        Ok(index) => Some(index),
+                        Err(_) => {
+                            arg_error("/deliverupto ITEM_INDEX|all");
+                            return;
+                        }
+                    },
+                    None => {
+                        arg_error("/deliverupto ITEM_INDEX|all");
+                        return;
+                    }
+                };
+
+                core.set_item_index_ceiling(ceiling);
+                core.log(RichText::Color {
+                    text: match ceiling {
+                        Some(ceiling) => {
+                            format!("Pausing item delivery before item index {}", ceiling)
+                        }
+                        None => "Delivering all items".into(),
+                    },
+                    color: ap::TextColor::Yellow,
+                });
+            }
+
+            #[cfg(debug_assertions)]
+            "/deathlink" => {
+                core.log(RichText::Color {
+                    text: "Simulating a received death link".into(),
+                    color: ap::TextColor::Yellow,
+                });
+                if let Err(err) = core.simulate_received_death_link() {
+                    core.log(RichText::Color {
+                        text: format!("Failed to simulate death link: {}", err),
+                        color: ap::TextColor::Red,
+                    });
+                }
+            }
+
+            #[cfg(debug_assertions)]
+            "/senddeathlink" => {
+                core.log(RichText::Color {
+                    text: "Simulating a death to send a death link".into(),
+                    color: ap::TextColor::Yellow,
+                });
+                if let Err(err) = core.simulate_sent_death_link() {
+                    core.log(RichText::Color {
+                        text: format!("Failed to simulate death: {}", err),
+                        color: ap::TextColor::Red,
+                    });
+                }
+            }
+
             #[cfg(debug_assertions)]
             "!setevent" => {
                 let Some((flag, value)) = arg().and_then(|a| {
@@ -528,18 +1592,18 @@ impl Overlay {
                 ]);
             }
 
-            _ => {
-                let _ = core.client_mut().unwrap().say(message);
-            }
+            _ => core.say(message),
         }
     }
 
     /// Returns whether the overlay is currently in "compact mode", where the
     /// bottommost widgets are not rendered.
     fn is_compact_mode(&self, core: &Core) -> bool {
-        if core.is_disconnected() {
+        if core.connection_state_type() != ap::ConnectionStateType::Connected {
             // When the connection is inactive, always show the buttons to
-            // reconnect.
+            // reconnect or cancel.
+            false
+        } else if self.chat_open {
             false
         } else if let Ok(menu_man) = unsafe { MenuMan::instance() } {
             !menu_man.is_menu_mode() && !self.is_main_menu()
@@ -557,6 +1621,63 @@ impl Overlay {
     }
 }
 
+/// Returns a short description of [core]'s connection state.
+fn connection_status(core: &Core) -> &'static str {
+    match core.connection_state_type() {
+        ap::ConnectionStateType::Connected => "Connected",
+        ap::ConnectionStateType::Connecting => "Connecting...",
+        ap::ConnectionStateType::Disconnected => "Disconnected",
+    }
+}
+
+/// The callback handler for the say input, which supports both scrolling
+/// through history and tab-completing command names.
+struct SayInputCallback<'a>(&'a mut TextInputHistory);
+
+impl InputTextCallbackHandler for SayInputCallback<'_> {
+    fn on_history(&mut self, dir: HistoryDirection, text: TextCallbackData) {
+        self.0.on_history(dir, text);
+    }
+
+    fn on_completion(&mut self, mut text: TextCallbackData) {
+        if let Some(completion) = complete_command(text.str()) {
+            text.clear();
+            text.push_str(&completion);
+        }
+    }
+}
+
+/// Returns the completion for the partially-typed command [prefix], or None if
+/// it can't be completed.
+///
+/// If multiple commands match, this completes as much as they have in common.
+/// If only one does, it also adds a space so the player can type arguments.
+fn complete_command(prefix: &str) -> Option<String> {
+    // Only complete the command name itself, not its arguments.
+    if !prefix.starts_with('!') || prefix.contains(' ') {
+        return None;
+    }
+
+    let mut matches = COMPLETABLE_COMMANDS
+        .iter()
+        .filter(|command| command.starts_with(prefix));
+    let mut completion = matches.next()?.to_string();
+    let mut unique = true;
+    for command in matches {
+        unique = false;
+        let common = completion
+            .chars()
+            .zip(command.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        completion.truncate(common);
+    }
+    if unique {
+        completion.push(' ');
+    }
+    Some(completion)
+}
+
 trait ImColor32Ext {
     /// Returns a copy of [self] with its opacity overridden by [alpha].
     fn with_alpha(&self, alpha: u8) -> ImColor32;
@@ -568,23 +1689,118 @@ impl ImColor32Ext for ImColor32 {
     }
 }
 
-/// Writes the text in [parts] to [ui] in a single line.
-fn write_message_data(ui: &Ui, parts: &[RichText], alpha: u8) {
+/// Returns whether [message] is relevant to the player: either a
+/// miscellaneous message from the server or an item message involving the
+/// player's slot. Irrelevant messages are de-emphasized in the log.
+fn is_relevant(core: &Core, message: &ap::Print) -> bool {
+    use ap::Print::*;
+    match message {
+        Chat { .. }
+        | ServerChat { .. }
+        | Tutorial { .. }
+        | CommandResult { .. }
+        | AdminCommandResult { .. }
+        | Unknown { .. } => true,
+        ItemSend { item, .. } | ItemCheat { item, .. } | Hint { item, .. } => {
+            core.config().slot() == item.receiver().name()
+                || core.config().slot() == item.sender().name()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the Archipelago item and location IDs referenced by [message] if it
+/// refers to an item.
+fn ids(message: &ap::Print) -> Option<(i64, i64)> {
+    use ap::Print::*;
+    match message {
+        ItemSend { item, .. } | ItemCheat { item, .. } | Hint { item, .. } => {
+            Some((item.item().id(), item.location().id()))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the color used to highlight items with the given [class].
+fn item_class_color(class: ItemClass) -> ImColor32 {
+    match class {
+        ItemClass::Progression => GREEN,
+        ItemClass::Useful => BLUE,
+        ItemClass::Trap => RED,
+        ItemClass::Filler => BLACK,
+    }
+}
+
+/// Returns the color to use for an item with the given [class], or the default
+/// item color if [class] is None or the item is [obscured].
+fn item_color(class: Option<ItemClass>, obscured: bool) -> ImColor32 {
+    match class {
+        // Coloring an obscured item by its classification would give away what
+        // kind of item it is.
+        Some(class) if !obscured => item_class_color(class),
+        _ => MAGENTA,
+    }
+}
+
+/// Returns the classification of the item referenced by [message], or None if
+/// it doesn't refer to an item.
+fn item_class(message: &ap::Print) -> Option<ItemClass> {
+    use ap::Print::*;
+    match message {
+        ItemSend { item, .. } | ItemCheat { item, .. } | Hint { item, .. } => {
+            Some(ItemClass::of(item.item()))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the plain text of [parts] as it's displayed in the log. If
+/// [obscure_items] is true, item names are replaced with placeholders so that
+/// searching doesn't reveal them.
+fn message_text(parts: &[RichText], obscure_items: bool) -> String {
+    parts
+        .iter()
+        .map(|part| match part {
+            RichText::Item { .. } if obscure_items => "???".to_string(),
+            _ => part.to_string(),
+        })
+        .collect()
+}
+
+/// Writes the text of [message] to [ui] in a single line.
+///
+/// Item names are colored according to their classification. If
+/// [obscure_items] is true, item names are replaced with placeholders and
+/// written in the default item color. If [show_ids] is true, the item and
+/// location IDs are displayed after the item and location names, respectively,
+/// except for obscured items. If [backgrounds] is true, parts with a
+/// background color are drawn over a rectangle of that color.
+fn write_message_data(
+    ui: &Ui,
+    message: &ap::Print,
+    obscure_items: bool,
+    show_ids: bool,
+    backgrounds: bool,
+    alpha: u8,
+) {
+    let item_color = item_color(item_class(message), obscure_items);
+    let ids = ids(message);
+
     let mut first = true;
-    for part in parts {
+    for part in message.data() {
         if !first {
             ui.same_line();
         }
         first = false;
 
         // TODO: Load in fonts to support bold, maybe write a line manually for
-        // underline? I'm not sure there's a reasonable way to support
-        // background colors.
+        // underline?
         use RichText::*;
         use TextColor::*;
         let color = match part {
             Player { .. } | PlayerName { .. } | Color { color: Blue, .. } => BLUE,
-            Item { .. } | Color { color: Magenta, .. } => MAGENTA,
+            Item { .. } => item_color,
+            Color { color: Magenta, .. } => MAGENTA,
             Location { .. } | EntranceName { .. } | Color { color: Cyan, .. } => CYAN,
             Color { color: Black, .. } => BLACK,
             Color { color: Red, .. } => RED,
@@ -592,6 +1808,93 @@ fn write_message_data(ui: &Ui, parts: &[RichText], alpha: u8) {
             Color { color: Yellow, .. } => YELLOW,
             _ => WHITE,
         };
-        ui.text_colored(color.with_alpha(alpha).to_rgba_f32s(), part.to_string());
+        let background = match part {
+            Color { color: BlackBg, .. } => Some(BLACK),
+            Color { color: RedBg, .. } => Some(RED),
+            Color { color: GreenBg, .. } => Some(GREEN),
+            Color {
+                color: YellowBg, ..
+            } => Some(YELLOW),
+            Color { color: BlueBg, .. } => Some(BLUE),
+            Color {
+                color: MagentaBg, ..
+            } => Some(MAGENTA),
+            Color { color: CyanBg, .. } => Some(CYAN),
+            Color { color: WhiteBg, .. } => Some(WHITE),
+            _ => None,
+        };
+        let mut text = match part {
+            Item { .. } if obscure_items => "???".to_string(),
+            Location { .. } => {
+                utils::location_name(&part.to_string(), ids.map(|(_, location)| location))
+            }
+            _ => part.to_string(),
+        };
+        match (part, ids) {
+            // The ID would identify an obscured item just as well as its name.
+            (Item { .. }, Some((item_id, _))) if show_ids && !obscure_items => {
+                text.push_str(&format!(" (#{})", item_id))
+            }
+            // Don't repeat the ID if it's already standing in for the name.
+            (Location { .. }, Some((_, location_id)))
+                if show_ids && !part.to_string().trim().is_empty() =>
+            {
+                text.push_str(&format!(" (#{})", location_id))
+            }
+            _ => {}
+        }
+
+        if backgrounds && let Some(background) = background {
+            // Each part is drawn on a single line, so its background is just
+            // the rectangle its text occupies. Dim it so the white text on top
+            // stays readable even against light colors.
+            let start = ui.cursor_screen_pos();
+            let size = ui.calc_text_size(&text);
+            ui.get_window_draw_list()
+                .add_rect(
+                    start,
+                    [start[0] + size[0], start[1] + size[1]],
+                    background.with_alpha((alpha as u16 * 0x60 / 0xFF) as u8),
+                )
+                .filled(true)
+                .build();
+        }
+        ui.text_colored(color.with_alpha(alpha).to_rgba_f32s(), text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_unique_command() {
+        assert_eq!(complete_command("!sta").as_deref(), Some("!status "));
+        assert_eq!(complete_command("!missing").as_deref(), Some("!missing "));
+    }
+
+    #[test]
+    fn complete_common_prefix() {
+        assert_eq!(complete_command("!c").as_deref(), Some("!c"));
+        assert_eq!(complete_command("!co").as_deref(), Some("!co"));
+        assert_eq!(complete_command("!h").as_deref(), Some("!h"));
+        assert_eq!(complete_command("!hi").as_deref(), Some("!hint"));
+        assert_eq!(complete_command("!re").as_deref(), Some("!re"));
+    }
+
+    #[test]
+    fn complete_nothing() {
+        assert_eq!(complete_command("!xyz"), None);
+        assert_eq!(complete_command("hint"), None);
+        assert_eq!(complete_command("!hint foo"), None);
+    }
+
+    #[test]
+    fn obscured_items_hide_their_class() {
+        assert_eq!(item_color(Some(ItemClass::Progression), false), GREEN);
+        assert_eq!(item_color(Some(ItemClass::Trap), false), RED);
+        assert_eq!(item_color(Some(ItemClass::Progression), true), MAGENTA);
+        assert_eq!(item_color(Some(ItemClass::Trap), true), MAGENTA);
+        assert_eq!(item_color(None, false), MAGENTA);
     }
 }
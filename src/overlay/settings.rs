@@ -0,0 +1,362 @@
+use std::{fs, io, path::PathBuf};
+
+use anyhow::{Result, bail};
+use archipelago_rs as ap;
+use imgui::Key;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+/// The smallest font scale the player can choose.
+pub const MIN_FONT_SCALE: f32 = 0.5;
+
+/// The largest font scale the player can choose.
+pub const MAX_FONT_SCALE: f32 = 4.0;
+
+/// Settings for the overlay UI that persist between sessions.
+///
+/// Unlike [Config], these only affect the overlay UI and not the behavior of
+/// the Archipelago connection, so they're stored in their own file.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct OverlaySettings {
+    /// The font scale for the overlay UI.
+    pub font_scale: f32,
+
+    /// The opacity of the overlay's background while it doesn't have focus,
+    /// from 0 to 1.
+    pub unfocused_window_opacity: f32,
+
+    /// Whether to show only a single status line rather than the full overlay.
+    pub status_line_only: bool,
+
+    /// Whether to block mouse input to the game while the mouse is over the
+    /// overlay. If this is false, the game always receives mouse input.
+    pub captures_mouse: bool,
+
+    /// Whether to display Archipelago item and location IDs next to their
+    /// names in the log. This is useful for debugging seeds.
+    pub show_ids: bool,
+
+    /// Whether to only auto-scroll the log for messages that are relevant to
+    /// this player, rather than for every new message.
+    pub auto_scroll_only_mine: bool,
+
+    /// Whether to show a ribbon of the most recently received items at the top
+    /// of the overlay.
+    pub show_recent_items: bool,
+
+    /// Whether to show a progress bar of how many of the player's locations
+    /// have been checked.
+    pub show_check_progress: bool,
+
+    /// Whether to highlight message parts that the server gives a background
+    /// color.
+    pub show_background_colors: bool,
+
+    /// Whether to briefly show a notification in the overlay for each item
+    /// the player receives.
+    pub show_item_toasts: bool,
+
+    /// Whether to show chat messages from players and the server in the log.
+    pub show_chat: bool,
+
+    /// Whether to show messages about items being sent in the log.
+    pub show_item_sends: bool,
+
+    /// Whether to show hints in the log.
+    pub show_hints: bool,
+
+    /// Whether to show the results of commands in the log.
+    pub show_command_results: bool,
+
+    /// Whether to show all other messages in the log, such as players joining
+    /// and leaving, tutorials, and messages from the client itself.
+    pub show_server_messages: bool,
+
+    /// The name of the function key (such as `"F9"`) that pauses and resumes
+    /// death link, or None if there's no hotkey.
+    pub death_link_hotkey: Option<String>,
+
+    /// The name of the function key (such as `"F8"`) that hides and shows the
+    /// overlay, or None if there's no hotkey.
+    pub toggle_overlay_hotkey: Option<String>,
+
+    /// The name of the function key that focuses the chat input, or None if
+    /// there's no hotkey.
+    pub focus_chat_hotkey: Option<String>,
+
+    /// The name of the function key that clears the overlay log, or None if
+    /// there's no hotkey.
+    pub clear_log_hotkey: Option<String>,
+
+    /// The position of the top-left corner of the main overlay window when it
+    /// was last moved, or None to use the default position.
+    pub window_position: Option<[f32; 2]>,
+
+    /// The size of the main overlay window (outside compact mode) when it was
+    /// last resized, or None to use the default size.
+    pub window_size: Option<[f32; 2]>,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            font_scale: 1.8,
+            unfocused_window_opacity: 0.4,
+            status_line_only: false,
+            captures_mouse: true,
+            show_ids: false,
+            auto_scroll_only_mine: false,
+            show_recent_items: true,
+            show_check_progress: true,
+            show_background_colors: true,
+            show_item_toasts: true,
+            show_chat: true,
+            show_item_sends: true,
+            show_hints: true,
+            show_command_results: true,
+            show_server_messages: true,
+            death_link_hotkey: Some("F9".into()),
+            toggle_overlay_hotkey: Some("F8".into()),
+            focus_chat_hotkey: None,
+            clear_log_hotkey: None,
+            window_position: None,
+            window_size: None,
+        }
+    }
+}
+
+impl OverlaySettings {
+    /// Loads the settings from disk, falling back to the defaults if they
+    /// don't exist or can't be loaded.
+    pub fn load() -> Self {
+        match Self::try_load() {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!("Failed to load overlay settings: {err}");
+                Default::default()
+            }
+        }
+    }
+
+    /// Like [load], but returns an error if the settings exist but can't be
+    /// loaded.
+    fn try_load() -> Result<Self> {
+        match fs::read_to_string(Self::path()?) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Parses settings from the JSON [text] of a settings file, clamping
+    /// values that were edited by hand to ranges the overlay can display.
+    fn parse(text: &str) -> Result<Self> {
+        let mut settings: Self = json::from_str(text)?;
+        settings.font_scale = settings.font_scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE);
+        settings.unfocused_window_opacity = settings.unfocused_window_opacity.clamp(0.0, 1.0);
+        Ok(settings)
+    }
+
+    /// Saves the settings to disk. Logs rather than returning errors, since
+    /// there's not much the player can do about them.
+    pub fn save(&self) {
+        let result = Self::path().and_then(|path| Ok(fs::write(path, json::to_string(self)?)?));
+        if let Err(err) = result {
+            warn!("Failed to save overlay settings: {err}");
+        }
+    }
+
+    /// Returns whether [message] should be displayed in the log according to
+    /// the player's category filters.
+    pub fn shows_in_log(&self, message: &ap::Print) -> bool {
+        use ap::Print::*;
+        match message {
+            Chat { .. } | ServerChat { .. } => self.show_chat,
+            ItemSend { .. } | ItemCheat { .. } => self.show_item_sends,
+            Hint { .. } => self.show_hints,
+            CommandResult { .. } | AdminCommandResult { .. } => self.show_command_results,
+            _ => self.show_server_messages,
+        }
+    }
+
+    /// Returns the name of the hotkey bound to [action], or None if it's
+    /// unbound.
+    pub fn hotkey(&self, action: HotkeyAction) -> Option<&str> {
+        use HotkeyAction::*;
+        match action {
+            ToggleOverlay => &self.toggle_overlay_hotkey,
+            FocusChat => &self.focus_chat_hotkey,
+            ClearLog => &self.clear_log_hotkey,
+            PauseDeathLink => &self.death_link_hotkey,
+        }
+        .as_deref()
+    }
+
+    /// Returns the ImGui key bound to [action], or None if it's unbound or
+    /// isn't a recognized key.
+    pub fn key(&self, action: HotkeyAction) -> Option<Key> {
+        parse_hotkey(self.hotkey(action)?)
+    }
+
+    /// Binds [action] to the hotkey named [name], or unbinds it if [name] is
+    /// None. Returns an error if another action is already bound to the same
+    /// key.
+    pub fn set_hotkey(&mut self, action: HotkeyAction, name: Option<&str>) -> Result<()> {
+        if let Some(name) = name
+            && let Some(other) = HotkeyAction::ALL.into_iter().find(|other| {
+                *other != action
+                    && self
+                        .hotkey(*other)
+                        .is_some_and(|hotkey| hotkey.eq_ignore_ascii_case(name))
+            })
+        {
+            bail!("{name} is already used for {}", other.label());
+        }
+
+        use HotkeyAction::*;
+        *match action {
+            ToggleOverlay => &mut self.toggle_overlay_hotkey,
+            FocusChat => &mut self.focus_chat_hotkey,
+            ClearLog => &mut self.clear_log_hotkey,
+            PauseDeathLink => &mut self.death_link_hotkey,
+        } = name.map(str::to_string);
+        Ok(())
+    }
+
+    /// The path to the settings file.
+    fn path() -> Result<PathBuf> {
+        Ok(utils::mod_directory()?.join("overlay-settings.json"))
+    }
+}
+
+/// An overlay action that can be bound to a hotkey.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    ToggleOverlay,
+    FocusChat,
+    ClearLog,
+    PauseDeathLink,
+}
+
+impl HotkeyAction {
+    /// All actions, in the order they're listed in the settings window.
+    pub const ALL: [HotkeyAction; 4] = [
+        HotkeyAction::ToggleOverlay,
+        HotkeyAction::FocusChat,
+        HotkeyAction::ClearLog,
+        HotkeyAction::PauseDeathLink,
+    ];
+
+    /// Returns the name of this action to show to the player.
+    pub fn label(self) -> &'static str {
+        use HotkeyAction::*;
+        match self {
+            ToggleOverlay => "Toggle Overlay",
+            FocusChat => "Focus Chat",
+            ClearLog => "Clear Log",
+            PauseDeathLink => "Pause DeathLink",
+        }
+    }
+}
+
+/// The keys that can be used as hotkeys, along with their names in the
+/// settings file.
+///
+/// These are limited to function keys because Dark Souls III doesn't use any
+/// of them by default, so binding them can't interfere with game input.
+pub const HOTKEYS: [(&str, Key); 12] = [
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+];
+
+/// Returns the ImGui key for the hotkey named [name], or None if it isn't a
+/// recognized key.
+fn parse_hotkey(name: &str) -> Option<Key> {
+    HOTKEYS
+        .iter()
+        .find(|(hotkey, _)| hotkey.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_clamps_font_scale() {
+        let settings = OverlaySettings::parse(r#"{"font_scale": 100.0}"#).unwrap();
+        assert_eq!(settings.font_scale, MAX_FONT_SCALE);
+        let settings = OverlaySettings::parse(r#"{"font_scale": 0.0}"#).unwrap();
+        assert_eq!(settings.font_scale, MIN_FONT_SCALE);
+        let settings = OverlaySettings::parse(r#"{"font_scale": 1.5}"#).unwrap();
+        assert_eq!(settings.font_scale, 1.5);
+    }
+
+    #[test]
+    fn parse_clamps_opacity() {
+        let settings = OverlaySettings::parse(r#"{"unfocused_window_opacity": 2.0}"#).unwrap();
+        assert_eq!(settings.unfocused_window_opacity, 1.0);
+    }
+
+    #[test]
+    fn set_hotkey_binds_unused_key() {
+        let mut settings = OverlaySettings::default();
+        settings
+            .set_hotkey(HotkeyAction::ClearLog, Some("F5"))
+            .unwrap();
+        assert_eq!(settings.hotkey(HotkeyAction::ClearLog), Some("F5"));
+        assert_eq!(settings.key(HotkeyAction::ClearLog), Some(Key::F5));
+    }
+
+    #[test]
+    fn set_hotkey_rejects_conflict() {
+        let mut settings = OverlaySettings::default();
+        assert!(
+            settings
+                .set_hotkey(HotkeyAction::ClearLog, Some("F9"))
+                .is_err()
+        );
+        // Conflicts are detected regardless of case.
+        assert!(
+            settings
+                .set_hotkey(HotkeyAction::ClearLog, Some("f8"))
+                .is_err()
+        );
+        assert_eq!(settings.hotkey(HotkeyAction::ClearLog), None);
+    }
+
+    #[test]
+    fn set_hotkey_allows_rebinding_same_action() {
+        let mut settings = OverlaySettings::default();
+        settings
+            .set_hotkey(HotkeyAction::PauseDeathLink, Some("F9"))
+            .unwrap();
+        assert_eq!(settings.hotkey(HotkeyAction::PauseDeathLink), Some("F9"));
+    }
+
+    #[test]
+    fn set_hotkey_unbinds() {
+        let mut settings = OverlaySettings::default();
+        settings
+            .set_hotkey(HotkeyAction::ToggleOverlay, None)
+            .unwrap();
+        assert_eq!(settings.hotkey(HotkeyAction::ToggleOverlay), None);
+        // The freed key can now be used by another action.
+        settings
+            .set_hotkey(HotkeyAction::FocusChat, Some("F8"))
+            .unwrap();
+    }
+}
@@ -1,10 +1,17 @@
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
 
+use anyhow::Result;
 use imgui::*;
+use log::*;
 
 /// The maximum number of messages to store in the history.
 const MAX_LENGTH: usize = 500;
 
+/// The maximum number of messages to save to disk for a persistent history.
+const PERSISTED_LENGTH: usize = 100;
+
 /// History for a single-line text input that's used repeatedly, such as a
 /// messenger input or a command prompt.
 #[derive(Default)]
@@ -15,6 +22,10 @@ pub struct TextInputHistory {
     /// The current index into [history]. None means that the user hasn't
     /// scrolled into the history at all.
     cursor: Option<usize>,
+
+    /// The file the history is saved to, or None if it only lasts for the
+    /// current session.
+    path: Option<PathBuf>,
 }
 
 impl TextInputHistory {
@@ -23,6 +34,37 @@ impl TextInputHistory {
         Default::default()
     }
 
+    /// Creates a history that's loaded from [path] and saved back to it
+    /// whenever a line is added, so it persists between sessions. Logs rather
+    /// than returning errors, since the history is a convenience.
+    pub fn persistent(path: PathBuf) -> Self {
+        let history = match Self::load(&path) {
+            Ok(history) => history,
+            Err(err) => {
+                warn!(
+                    "Failed to load input history from {}: {err}",
+                    path.display()
+                );
+                Default::default()
+            }
+        };
+        Self {
+            history,
+            cursor: None,
+            path: Some(path),
+        }
+    }
+
+    /// Loads a saved history from [path], returning an empty history if it
+    /// doesn't exist.
+    fn load(path: &Path) -> Result<VecDeque<String>> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(json::from_str(&text)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     /// Adds `line` to this input's history.
     pub fn add(&mut self, line: String) {
         if self.history.len() >= MAX_LENGTH {
@@ -30,9 +72,39 @@ impl TextInputHistory {
         }
         self.history.push_front(line);
         self.cursor = None;
+        self.save();
+    }
+
+    /// Saves the most recent lines to [path], if this history is persistent.
+    /// Lines that may contain secrets are kept in memory but never written.
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let lines = self
+            .history
+            .iter()
+            .filter(|line| should_persist(line))
+            .take(PERSISTED_LENGTH)
+            .collect::<Vec<_>>();
+        let result = json::to_string(&lines)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| Ok(fs::write(path, text)?));
+        if let Err(err) = result {
+            warn!("Failed to save input history to {}: {err}", path.display());
+        }
     }
 }
 
+/// Returns whether [line] is safe to write to disk. `!admin` commands include
+/// the server password, so they're never saved.
+fn should_persist(line: &str) -> bool {
+    !line
+        .trim_start()
+        .get(..6)
+        .is_some_and(|command| command.eq_ignore_ascii_case("!admin"))
+}
+
 impl InputTextCallbackHandler for &mut TextInputHistory {
     fn on_history(&mut self, dir: HistoryDirection, mut text: TextCallbackData) {
         if dir == HistoryDirection::Up {
@@ -54,3 +126,22 @@ impl InputTextCallbackHandler for &mut TextInputHistory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_ordinary_lines() {
+        assert!(should_persist("hello"));
+        assert!(should_persist("!hint Claw"));
+        assert!(should_persist("!adm"));
+    }
+
+    #[test]
+    fn does_not_persist_admin_commands() {
+        assert!(!should_persist("!admin login hunter2"));
+        assert!(!should_persist("  !admin /send Player Item"));
+        assert!(!should_persist("!ADMIN login hunter2"));
+    }
+}
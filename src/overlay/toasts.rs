@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use imgui::*;
+
+use super::{BLACK, ImColor32Ext, WHITE, item_class_color};
+use crate::core::GrantedItem;
+
+/// How long each toast stays visible.
+const LIFETIME: Duration = Duration::from_secs(4);
+
+/// How long a toast takes to fade out at the end of its [LIFETIME].
+const FADE_TIME: Duration = Duration::from_secs(1);
+
+/// The maximum number of toasts to show at once. Older toasts are dropped
+/// early to make room for new ones.
+const MAX_TOASTS: usize = 5;
+
+/// A short-lived stack of notifications for items the player has received.
+#[derive(Default)]
+pub struct Toasts {
+    /// The visible toasts, from newest to oldest, along with the time each was
+    /// added.
+    toasts: VecDeque<(GrantedItem, Instant)>,
+}
+
+impl Toasts {
+    /// Adds a toast for [item].
+    pub fn push(&mut self, item: GrantedItem) {
+        if self.toasts.len() >= MAX_TOASTS {
+            self.toasts.pop_back();
+        }
+        self.toasts.push_front((item, Instant::now()));
+    }
+
+    /// Renders all visible toasts in a window whose top-right corner is at
+    /// [position].
+    ///
+    /// The text is sized using the global font scale, so it matches the rest
+    /// of the overlay.
+    pub fn render(&mut self, ui: &Ui, position: [f32; 2]) {
+        self.toasts.retain(|(_, time)| time.elapsed() < LIFETIME);
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let _bg = ui.push_style_color(StyleColor::WindowBg, [0.0, 0.0, 0.0, 0.6]);
+        ui.window("##ap-item-toasts")
+            .position(position, Condition::Always)
+            .position_pivot([1., 0.])
+            .title_bar(false)
+            .resizable(false)
+            .movable(false)
+            .scroll_bar(false)
+            .always_auto_resize(true)
+            .mouse_inputs(false)
+            .focus_on_appearing(false)
+            .no_nav()
+            .build(|| {
+                for (item, time) in &self.toasts {
+                    let remaining = LIFETIME.saturating_sub(time.elapsed());
+                    let alpha = (remaining.as_secs_f32() / FADE_TIME.as_secs_f32()).min(1.0) * 255.;
+                    let alpha = alpha as u8;
+
+                    ui.text_colored(WHITE.with_alpha(alpha).to_rgba_f32s(), "Received");
+                    ui.same_line();
+                    ui.text_colored(
+                        item_class_color(item.class)
+                            .with_alpha(alpha)
+                            .to_rgba_f32s(),
+                        &item.name,
+                    );
+                    ui.same_line();
+                    ui.text_colored(
+                        BLACK.with_alpha(alpha).to_rgba_f32s(),
+                        format!("from {}", item.sender),
+                    );
+                }
+            });
+    }
+}
@@ -1,7 +1,10 @@
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use bincode::{Decode, Encode};
+use anyhow::Result;
+use bincode::de::{Decode, Decoder};
+use bincode::{Encode, error::DecodeError};
 use darksouls3::sprj::MapItemMan;
 use darksouls3_extra::save;
 use fromsoftware_shared::FromStatic;
@@ -11,11 +14,33 @@ use log::*;
 /// from the save file or set explicitly.
 static INSTANCE: LazyLock<RwLock<SaveData>> = LazyLock::new(|| RwLock::new(Default::default()));
 
+/// Whether the most recently loaded save file had no Archipelago data embedded
+/// in it, which means it was created before the mod was installed. This is
+/// distinct from starting a new game, which doesn't load a save file at all.
+static LOADED_WITHOUT_DATA: AtomicBool = AtomicBool::new(false);
+
 /// The configuration for the binary encoding of the save data.
 const CONFIG: bincode::config::Configuration = bincode::config::standard();
 
+/// The kinds of load that affect the [SaveData], distinguished from the
+/// game's own notion so that they can be handled in tests.
+enum Load<'a> {
+    /// The player loaded a save with these bytes of Archipelago data.
+    SavedData(&'a [u8]),
+
+    /// The player returned to the main menu.
+    MainMenu,
+
+    /// The player loaded a save with no Archipelago data.
+    WithoutData,
+}
+
 /// Data that's saved and loaded along with the player's game save.
-#[derive(Debug, Decode, Encode, Default)]
+///
+/// New fields must always be added at the end of this struct and to the end of
+/// its [Decode] implementation so that saves from older versions of the mod can
+/// still be loaded.
+#[derive(Debug, Encode, Default)]
 pub struct SaveData {
     /// The number of Archipelago items that have been granted to this player
     /// from foreign games throughout the course of this run.
@@ -34,6 +59,49 @@ pub struct SaveData {
     /// The number of deaths that player has experienced since last sending a
     /// death link.
     pub deaths: u8,
+
+    /// The subset of [locations] that the server has confirmed it's received.
+    /// These don't need to be sent again after a reconnect.
+    pub acknowledged_locations: HashSet<i64>,
+
+    /// The indices of trap items that were set aside because another trap had
+    /// been granted too recently, in the order they were received. These are
+    /// counted in [items_granted] but haven't actually been granted yet.
+    pub deferred_traps: Vec<usize>,
+
+    /// Whether the goal message and any automatic `!release` or `!collect`
+    /// have been sent for this save. Unlike the goal status itself, these
+    /// shouldn't be repeated every time the player starts the game.
+    pub announced_goal: bool,
+}
+
+impl<Context> Decode<Context> for SaveData {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(SaveData {
+            items_granted: Decode::decode(decoder)?,
+            locations: Decode::decode(decoder)?,
+            seed: Decode::decode(decoder)?,
+            deaths: Decode::decode(decoder)?,
+
+            // Fields added after 4.0.0-rc.3.
+            acknowledged_locations: decode_or_default(decoder)?,
+            deferred_traps: decode_or_default(decoder)?,
+            announced_goal: decode_or_default(decoder)?,
+        })
+    }
+}
+
+/// Decodes a field that may not exist in saves from older versions of the mod,
+/// returning the default value if the save data ends before it.
+fn decode_or_default<Context, D, T>(decoder: &mut D) -> Result<T, DecodeError>
+where
+    D: Decoder<Context = Context>,
+    T: Decode<Context> + Default,
+{
+    match T::decode(decoder) {
+        Err(DecodeError::UnexpectedEnd { .. }) => Ok(Default::default()),
+        result => result,
+    }
 }
 
 impl SaveData {
@@ -55,41 +123,80 @@ impl SaveData {
                 },
                 |load_type| {
                     use save::OnLoadType::*;
-                    let bytes = match load_type {
-                        SavedData(bytes) => bytes,
-                        MainMenu => {
-                            // If the player goes back to the main menu, reset
-                            // the granted items and seed info so that if the
-                            // user starts a new file they get all new items and
-                            // no seed conflict.
-                            let mut save = INSTANCE.write().unwrap();
-                            save.items_granted = 0;
-                            save.seed = None;
-                            return;
-                        }
-                        _ => return,
-                    };
-
-                    match bincode::decode_from_slice(&bytes, CONFIG) {
-                        Ok((data, size)) => {
-                            if size == bytes.len() {
-                                *INSTANCE.write().unwrap() = data;
-                            } else {
-                                warn!(
-                                    "Archipelago save data had {} extra bytes! This probably means \
-                                 that you tried to load a save file created by a different version \
-                                 of the Archipelago mod, or by a different mod entirely.",
-                                    bytes.len() - size
-                                );
-                            }
-                        }
-                        Err(err) => warn!("Failed to load save data: {}", err),
+                    match load_type {
+                        SavedData(bytes) => Self::load(Load::SavedData(&bytes)),
+                        MainMenu => Self::load(Load::MainMenu),
+                        _ => Self::load(Load::WithoutData),
                     }
                 },
             ));
         }
     }
 
+    /// Updates the singleton [SaveData] for the game loading [load].
+    fn load(load: Load) {
+        let bytes = match load {
+            Load::SavedData(bytes) => {
+                LOADED_WITHOUT_DATA.store(false, Ordering::Relaxed);
+                bytes
+            }
+            Load::MainMenu => {
+                LOADED_WITHOUT_DATA.store(false, Ordering::Relaxed);
+
+                // If the player goes back to the main menu, reset the granted
+                // items and seed info so that if the user starts a new file
+                // they get all new items and no seed conflict.
+                let mut save = INSTANCE.write().unwrap();
+                save.items_granted = 0;
+                save.deferred_traps.clear();
+                save.announced_goal = false;
+                save.seed = None;
+                return;
+            }
+            Load::WithoutData => {
+                // The player loaded a save that doesn't have any Archipelago
+                // data, most likely because it was created before the mod was
+                // installed. Don't carry over data from whatever was loaded
+                // before.
+                info!("Loaded a save without Archipelago data");
+                LOADED_WITHOUT_DATA.store(true, Ordering::Relaxed);
+                *INSTANCE.write().unwrap() = Default::default();
+                return;
+            }
+        };
+
+        match Self::from_bytes(bytes) {
+            Ok(data) => *INSTANCE.write().unwrap() = data,
+            Err(err) => warn!("Failed to load save data: {}", err),
+        }
+    }
+
+    /// Decodes save data from the bytes embedded in a save file.
+    ///
+    /// Because fields are only ever appended, bytes left over after decoding
+    /// most likely mean that this save was written by a newer version of the
+    /// mod. In that case, this keeps the fields this version understands
+    /// rather than losing the player's progress entirely.
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (data, size) = bincode::decode_from_slice(bytes, CONFIG)?;
+        if size != bytes.len() {
+            warn!(
+                "Archipelago save data had {} extra bytes! This probably means that it was \
+                 created by a newer version of the Archipelago mod. Loading the data this version \
+                 understands; the rest will be lost the next time the game saves.",
+                bytes.len() - size
+            );
+        }
+        Ok(data)
+    }
+
+    /// Returns whether the save the player most recently loaded had no
+    /// Archipelago data embedded in it. Such a save shouldn't automatically be
+    /// associated with a seed, since it may belong to an unrelated playthrough.
+    pub fn loaded_without_data() -> bool {
+        LOADED_WITHOUT_DATA.load(Ordering::Relaxed)
+    }
+
     /// Returns a read-only reference to the singleton [SaveData], or None if
     /// the player isn't currently loaded into a game.
     pub fn instance<'a>() -> Option<RwLockReadGuard<'a, Self>> {
@@ -118,3 +225,149 @@ impl SaveData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SaveData {
+        SaveData {
+            items_granted: 12,
+            locations: HashSet::from([1, 2, 3]),
+            seed: Some("12345".to_string()),
+            deaths: 2,
+            acknowledged_locations: HashSet::from([1, 2]),
+            deferred_traps: vec![7, 9],
+            announced_goal: true,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = bincode::encode_to_vec(sample(), CONFIG).unwrap();
+        let data = SaveData::from_bytes(&bytes).unwrap();
+        assert_eq!(data.items_granted, 12);
+        assert_eq!(data.locations, HashSet::from([1, 2, 3]));
+        assert_eq!(data.seed.as_deref(), Some("12345"));
+        assert_eq!(data.deaths, 2);
+        assert_eq!(data.acknowledged_locations, HashSet::from([1, 2]));
+        assert_eq!(data.deferred_traps, vec![7, 9]);
+        assert!(data.announced_goal);
+    }
+
+    #[test]
+    fn large_location_set_round_trip() {
+        // More locations than any single DS3 world has, with IDs in the same
+        // range as the apworld's.
+        let locations = (100_000..101_000).collect::<HashSet<i64>>();
+        let data = SaveData {
+            locations: locations.clone(),
+            ..sample()
+        };
+        let bytes = bincode::encode_to_vec(data, CONFIG).unwrap();
+
+        // Varint encoding keeps each of these IDs to 5 bytes, so this is small
+        // enough to encode synchronously on every save.
+        assert!(bytes.len() < 6 * 1024, "encoded to {} bytes", bytes.len());
+        assert_eq!(SaveData::from_bytes(&bytes).unwrap().locations, locations);
+    }
+
+    #[test]
+    fn missing_trailing_field_is_default() {
+        // Encode everything but the last field, as an older version would.
+        let bytes = bincode::encode_to_vec(
+            (
+                12usize,
+                HashSet::from([1i64, 2, 3]),
+                Some("12345".to_string()),
+                2u8,
+                HashSet::from([1i64, 2]),
+            ),
+            CONFIG,
+        )
+        .unwrap();
+        let data = SaveData::from_bytes(&bytes).unwrap();
+        assert_eq!(data.acknowledged_locations, HashSet::from([1, 2]));
+        assert!(data.deferred_traps.is_empty());
+    }
+
+    #[test]
+    fn decodes_rc3_save() {
+        // 4.0.0-rc.3 saves end after the death count.
+        let bytes = bincode::encode_to_vec(
+            (
+                12usize,
+                HashSet::from([1i64, 2, 3]),
+                Some("12345".to_string()),
+                2u8,
+            ),
+            CONFIG,
+        )
+        .unwrap();
+        let data = SaveData::from_bytes(&bytes).unwrap();
+        assert_eq!(data.items_granted, 12);
+        assert_eq!(data.locations, HashSet::from([1, 2, 3]));
+        assert_eq!(data.seed.as_deref(), Some("12345"));
+        assert_eq!(data.deaths, 2);
+        assert!(data.acknowledged_locations.is_empty());
+        assert!(data.deferred_traps.is_empty());
+        assert!(!data.announced_goal);
+    }
+
+    #[test]
+    fn truncated_required_field_is_error() {
+        let bytes = bincode::encode_to_vec((12usize, HashSet::from([1i64])), CONFIG).unwrap();
+        assert!(SaveData::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn decodes_newer_save() {
+        // A newer version of the mod would append its new fields to the end.
+        let mut bytes = bincode::encode_to_vec(sample(), CONFIG).unwrap();
+        bytes.extend(bincode::encode_to_vec(("new field", 42u64), CONFIG).unwrap());
+
+        let data = SaveData::from_bytes(&bytes).unwrap();
+        assert_eq!(data.items_granted, 12);
+        assert_eq!(data.locations, HashSet::from([1, 2, 3]));
+        assert_eq!(data.seed.as_deref(), Some("12345"));
+        assert_eq!(data.deaths, 2);
+        assert_eq!(data.acknowledged_locations, HashSet::from([1, 2]));
+        assert_eq!(data.deferred_traps, vec![7, 9]);
+        assert!(data.announced_goal);
+    }
+
+    // The load handlers update global state, so they're all tested in one
+    // function to keep them from running in parallel.
+    #[test]
+    fn load_updates_instance() {
+        let bytes = bincode::encode_to_vec(sample(), CONFIG).unwrap();
+        SaveData::load(Load::SavedData(&bytes));
+        assert!(!SaveData::loaded_without_data());
+        assert_eq!(INSTANCE.read().unwrap().items_granted, 12);
+
+        SaveData::load(Load::WithoutData);
+        assert!(SaveData::loaded_without_data());
+        {
+            let data = INSTANCE.read().unwrap();
+            assert_eq!(data.items_granted, 0);
+            assert!(data.locations.is_empty());
+            assert_eq!(data.seed, None);
+            assert_eq!(data.deaths, 0);
+            assert!(data.acknowledged_locations.is_empty());
+            assert!(data.deferred_traps.is_empty());
+            assert!(!data.announced_goal);
+        }
+
+        SaveData::load(Load::SavedData(&bytes));
+        assert!(!SaveData::loaded_without_data());
+        SaveData::load(Load::MainMenu);
+        assert!(!SaveData::loaded_without_data());
+        {
+            let data = INSTANCE.read().unwrap();
+            assert_eq!(data.items_granted, 0);
+            assert_eq!(data.seed, None);
+            assert!(data.deferred_traps.is_empty());
+            assert!(!data.announced_goal);
+        }
+    }
+}
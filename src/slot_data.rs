@@ -16,6 +16,12 @@ pub struct SlotData {
     #[serde(deserialize_with = "deserialize_goal")]
     pub goal: Vec<EventFlag>,
 
+    /// A human-readable description of [goal], such as "Defeat all bosses".
+    ///
+    /// This is `None` for apworlds that don't provide this information.
+    #[serde(default)]
+    pub goal_description: Option<String>,
+
     /// A map from Archipelago's item IDs to DS3's.
     pub ap_ids_to_item_ids: HashMap<I64Key, DeserializableItemId>,
 
@@ -23,6 +29,27 @@ pub struct SlotData {
     /// item the given ID should grant.
     pub item_counts: HashMap<I64Key, u32>,
 
+    /// A map from the names of progression items that this player will receive
+    /// from the server to the number of copies of each. This doesn't include
+    /// items placed in this player's own world, since those are picked up
+    /// in-game rather than sent by the server.
+    ///
+    /// This is `None` for apworlds that don't provide this information.
+    #[serde(default)]
+    pub progression_items: Option<HashMap<String, u32>>,
+
+    /// A map from DS3 goods IDs to the IDs of gestures they should grant, for
+    /// gesture items beyond those the client knows about natively.
+    #[serde(default)]
+    pub gesture_items: HashMap<I64Key, u32>,
+
+    /// A map from Archipelago item IDs to the names of special effects that
+    /// should be applied when those items are received. If an item also has a
+    /// DS3 ID in [ap_ids_to_item_ids], it's granted as well as applying the
+    /// effect. See [ItemEffect] for the available effects.
+    #[serde(default)]
+    pub item_effects: HashMap<I64Key, String>,
+
     /// The options chosen by this player.
     pub options: Options,
 }
@@ -44,7 +71,7 @@ fn deserialize_goal<'de, D: Deserializer<'de>>(
 
 /// The default goal, used because the DS3 AP 3.x world doesn't provide a list
 /// of goal events.
-fn default_goal() -> Vec<EventFlag> {
+pub fn default_goal() -> Vec<EventFlag> {
     vec![14100800.try_into().unwrap()]
 }
 
@@ -61,6 +88,11 @@ pub struct Options {
     /// How many deaths it takes to send a death link.
     #[serde(default = "default_death_link_amnesty")]
     pub death_link_amnesty: u8,
+
+    /// Whether to hide the names of items received from other worlds in the
+    /// overlay until they've been granted in-game.
+    #[serde(default, deserialize_with = "int_to_bool")]
+    pub obscure_received_items: bool,
 }
 
 /// Deserializes an integer as a boolean value.
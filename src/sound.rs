@@ -0,0 +1,21 @@
+use log::*;
+use windows::Win32::UI::WindowsAndMessaging::{MB_ICONASTERISK, MB_ICONHAND, MessageBeep};
+
+use crate::core::ItemClass;
+
+/// Plays the sound cue for receiving an item of [class], if it has one:
+/// a chime for progression items and a buzzer for traps.
+///
+/// This uses Windows's system sounds, which play asynchronously and respect
+/// the player's sound scheme and volume settings.
+pub fn play_item_cue(class: ItemClass) {
+    let style = match class {
+        ItemClass::Progression => MB_ICONASTERISK,
+        ItemClass::Trap => MB_ICONHAND,
+        ItemClass::Useful | ItemClass::Filler => return,
+    };
+
+    if let Err(err) = unsafe { MessageBeep(style) } {
+        warn!("Failed to play item sound cue: {err}");
+    }
+}
@@ -27,6 +27,29 @@ pub fn mod_directory<'a>() -> Result<&'a Path> {
     }
 }
 
+/// Returns whether the current process is Dark Souls III.
+pub fn is_dark_souls_3() -> bool {
+    std::env::current_exe().is_ok_and(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.eq_ignore_ascii_case("DarkSoulsIII.exe"))
+    })
+}
+
+/// Returns [name] if it's a usable location name, or a description based on the
+/// numeric [id] (if it's known) otherwise.
+///
+/// The data package isn't guaranteed to include every location, especially for
+/// other games' worlds or locations that were added after the data package was
+/// cached, so any location name displayed to the user should go through this.
+pub fn location_name(name: &str, id: Option<i64>) -> String {
+    match id {
+        _ if !name.trim().is_empty() => name.to_string(),
+        Some(id) => format!("location {}", id),
+        None => "unknown location".to_string(),
+    }
+}
+
 /// Loads [mod_directory] without caching.
 fn load_mod_directory() -> Result<PathBuf> {
     println!("Locating mod directory...");
@@ -149,3 +172,30 @@ impl<Label> PopupModalExt for PopupModal<'_, '_, Label> {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_name_keeps_real_names() {
+        assert_eq!(
+            location_name("FS: Coiled Sword", Some(123)),
+            "FS: Coiled Sword"
+        );
+        assert_eq!(location_name("FS: Coiled Sword", None), "FS: Coiled Sword");
+        // Names are never guessed to be placeholders based on their text.
+        assert_eq!(location_name("Unknown Shrine", Some(123)), "Unknown Shrine");
+    }
+
+    #[test]
+    fn location_name_falls_back_to_id() {
+        assert_eq!(location_name("", Some(123)), "location 123");
+        assert_eq!(location_name("   ", Some(123)), "location 123");
+    }
+
+    #[test]
+    fn location_name_without_id() {
+        assert_eq!(location_name("", None), "unknown location");
+    }
+}